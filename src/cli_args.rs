@@ -9,9 +9,18 @@ use ulid::Ulid;
 
 use crate::{
   client::Client,
-  commands::{ChangeFileCommand, CloseFileCommand, HoverFileCommand, NewSessionCommand, OpenFileCommand},
+  commands::{
+    ApplyCodeActionCommand, ApplyWorkspaceEditCommand, ChangeFileCommand, CloseFileCommand, EditFileCommand,
+    ExpandTraceChildrenCommand, GetCallHierarchyCommand, GetCodeActionsCommand, GetCompletionsCommand,
+    GetDiagnosticsCommand, GetDocumentHighlightsCommand, GetDocumentSymbolsCommand, GetFileCommand,
+    GetFoldingRangesCommand, GetInlayHintsCommand, GetInteractiveDiagnosticsCommand, GetInteractiveGoalsCommand,
+    GetReferencesCommand, GetSemanticTokensCommand, GetSemanticTokensRangeCommand, GetWorkspaceSymbolsCommand,
+    GoToCommand, HoverFileCommand, LeanRpcCallCommand, LeanRpcReleaseCommand, NewSessionCommand, OpenFileCommand,
+    PrepareRenameCommand, RawMessageCommand, RenameCommand, ReplyToServerRequestCommand, ResolveCompletionItemCommand,
+    SaveFileCommand, WaitForFileCommand,
+  },
   server::Server,
-  types::Location,
+  types::{CallHierarchyItem, Location},
 };
 
 #[derive(Args)]
@@ -68,6 +77,42 @@ impl New {
   }
 }
 
+#[derive(Args)]
+struct Rename {
+  #[command(flatten)]
+  command: RenameCommand,
+
+  /// Only print the diff of the rename without applying it
+  #[arg(long)]
+  dry_run: bool,
+
+  /// Also write the renamed files that are open in the session to disk
+  #[arg(long)]
+  write: bool,
+}
+
+impl Rename {
+  async fn run(self, client: &Client) -> Result<(), AnyhowError> {
+    let rename_response = client.rename(&self.command).await?;
+
+    for file_diff in &rename_response.diffs {
+      file_diff.diff.println();
+    }
+
+    if self.dry_run {
+      return ().ok();
+    }
+
+    let apply_workspace_edit_command = ApplyWorkspaceEditCommand {
+      session_id: self.command.session_id,
+      workspace_edit: rename_response.workspace_edit,
+      write: self.write,
+    };
+
+    client.apply_workspace_edit(&apply_workspace_edit_command).await?.ok()
+  }
+}
+
 #[derive(Args)]
 struct File {
   #[arg(long, default_value_t = Server::DEFAULT_PORT)]
@@ -77,14 +122,155 @@ struct File {
 }
 
 impl File {
+  const CALL_HIERARCHY_INDENT: &'static str = "  ";
+  const CALL_HIERARCHY_RECURSIVE_SUFFIX: &'static str = " (recursive)";
+
+  fn print_call_hierarchy(items: &[CallHierarchyItem], level: usize) {
+    let indent = Self::CALL_HIERARCHY_INDENT.repeat(level);
+
+    for item in items {
+      let start = &item.selection_range.start;
+
+      let recursive = if item.is_recursive {
+        Self::CALL_HIERARCHY_RECURSIVE_SUFFIX
+      } else {
+        ""
+      };
+
+      format!(
+        "{indent}{name} {filepath}:{line}:{character}{recursive}",
+        name = item.name,
+        filepath = item.location.filepath.display(),
+        line = start.line,
+        character = start.character,
+      )
+      .println();
+
+      Self::print_call_hierarchy(&item.calls, level + 1);
+    }
+  }
+
   async fn run(self) -> Result<(), AnyhowError> {
     let client = Client::new(self.port)?;
 
     match self.command {
-      FileCommand::Open(open_command) => client.open_file(&open_command).await?.ok(),
+      FileCommand::Open(open_command) => client.open_file(&open_command).await?.to_json_str()?.println().ok(),
       FileCommand::Change(change_command) => client.change_file(change_command).await?.ok(),
+      FileCommand::Edit(edit_command) => client.edit_file(edit_command).await?.ok(),
+      FileCommand::Get(get_command) => client.get_file(&get_command).await?.to_json_str()?.println().ok(),
+      FileCommand::Save(save_command) => client.save_file(&save_command).await?.ok(),
       FileCommand::Close(close_command) => client.close_file(&close_command).await?.ok(),
       FileCommand::Hover(hover_command) => client.hover_file(&hover_command).await?.to_json_str()?.println().ok(),
+      FileCommand::Definition(go_to_command) => client
+        .get_definition(&go_to_command)
+        .await?
+        .to_json_str()?
+        .println()
+        .ok(),
+      FileCommand::Declaration(go_to_command) => client
+        .get_declaration(&go_to_command)
+        .await?
+        .to_json_str()?
+        .println()
+        .ok(),
+      FileCommand::TypeDefinition(go_to_command) => client
+        .get_type_definition(&go_to_command)
+        .await?
+        .to_json_str()?
+        .println()
+        .ok(),
+      FileCommand::References(references_command) => client
+        .get_references(&references_command)
+        .await?
+        .to_json_str()?
+        .println()
+        .ok(),
+      FileCommand::PrepareRename(prepare_rename_command) => client
+        .prepare_rename(&prepare_rename_command)
+        .await?
+        .to_json_str()?
+        .println()
+        .ok(),
+      FileCommand::Rename(rename) => rename.run(&client).await,
+      FileCommand::Completion(completion_command) => client
+        .get_completions(&completion_command)
+        .await?
+        .to_json_str()?
+        .println()
+        .ok(),
+      FileCommand::ResolveCompletion(resolve_command) => client
+        .resolve_completion_item(&resolve_command)
+        .await?
+        .item
+        .to_json_str()?
+        .println()
+        .ok(),
+      FileCommand::Symbols(symbols_command) => client
+        .get_document_symbols(&symbols_command)
+        .await?
+        .to_json_str()?
+        .println()
+        .ok(),
+      FileCommand::CodeActions(code_actions_command) => client
+        .get_code_actions(&code_actions_command)
+        .await?
+        .to_json_str()?
+        .println()
+        .ok(),
+      FileCommand::FoldingRanges(folding_ranges_command) => client
+        .get_folding_ranges(&folding_ranges_command)
+        .await?
+        .to_json_str()?
+        .println()
+        .ok(),
+      FileCommand::Calls(call_hierarchy_command) => {
+        let call_hierarchy = client.get_call_hierarchy(&call_hierarchy_command).await?;
+
+        Self::print_call_hierarchy(&call_hierarchy.items, 0).ok()
+      }
+      FileCommand::InlayHints(inlay_hints_command) => client
+        .get_inlay_hints(&inlay_hints_command)
+        .await?
+        .to_json_str()?
+        .println()
+        .ok(),
+      FileCommand::Highlights(highlights_command) => client
+        .get_document_highlights(&highlights_command)
+        .await?
+        .to_json_str()?
+        .println()
+        .ok(),
+      FileCommand::SemanticTokens(semantic_tokens_command) => client
+        .get_semantic_tokens(&semantic_tokens_command)
+        .await?
+        .to_json_str()?
+        .println()
+        .ok(),
+      FileCommand::SemanticTokensRange(semantic_tokens_range_command) => client
+        .get_semantic_tokens_range(&semantic_tokens_range_command)
+        .await?
+        .to_json_str()?
+        .println()
+        .ok(),
+      FileCommand::Diagnostics(diagnostics_command) => client
+        .get_diagnostics(&diagnostics_command)
+        .await?
+        .to_json_str()?
+        .println()
+        .ok(),
+      FileCommand::InteractiveDiagnostics(interactive_diagnostics_command) => client
+        .get_interactive_diagnostics(&interactive_diagnostics_command)
+        .await?
+        .to_json_str()?
+        .println()
+        .ok(),
+      FileCommand::TraceChildren(trace_children_command) => client
+        .expand_trace_children(&trace_children_command)
+        .await?
+        .to_json_str()?
+        .println()
+        .ok(),
+      FileCommand::Wait(wait_command) => client.wait_for_file(&wait_command).await?.to_json_str()?.println().ok(),
     }
   }
 }
@@ -94,7 +280,30 @@ enum FileCommand {
   Open(OpenFileCommand),
   Hover(HoverFileCommand),
   Change(ChangeFileCommand),
+  Edit(EditFileCommand),
+  Get(GetFileCommand),
+  Save(SaveFileCommand),
   Close(CloseFileCommand),
+  Definition(GoToCommand),
+  Declaration(GoToCommand),
+  TypeDefinition(GoToCommand),
+  References(GetReferencesCommand),
+  PrepareRename(PrepareRenameCommand),
+  Rename(Rename),
+  Completion(GetCompletionsCommand),
+  ResolveCompletion(ResolveCompletionItemCommand),
+  Symbols(GetDocumentSymbolsCommand),
+  CodeActions(GetCodeActionsCommand),
+  FoldingRanges(GetFoldingRangesCommand),
+  Calls(GetCallHierarchyCommand),
+  InlayHints(GetInlayHintsCommand),
+  Highlights(GetDocumentHighlightsCommand),
+  SemanticTokens(GetSemanticTokensCommand),
+  SemanticTokensRange(GetSemanticTokensRangeCommand),
+  Diagnostics(GetDiagnosticsCommand),
+  InteractiveDiagnostics(GetInteractiveDiagnosticsCommand),
+  TraceChildren(ExpandTraceChildrenCommand),
+  Wait(WaitForFileCommand),
 }
 
 #[derive(Args)]
@@ -121,6 +330,134 @@ impl Notifications {
   }
 }
 
+#[derive(Args)]
+struct Reply {
+  #[arg(long, default_value_t = Server::DEFAULT_PORT)]
+  port: u16,
+  #[command(flatten)]
+  command: ReplyToServerRequestCommand,
+}
+
+impl Reply {
+  async fn run(self) -> Result<(), AnyhowError> {
+    Client::new(self.port)?
+      .reply_to_server_request(&self.command)
+      .await?
+      .ok()
+  }
+}
+
+#[derive(Subcommand)]
+enum RawCommand {
+  Request(RawMessageCommand),
+  Notify(RawMessageCommand),
+}
+
+#[derive(Args)]
+struct Raw {
+  #[arg(long, default_value_t = Server::DEFAULT_PORT)]
+  port: u16,
+  #[command(subcommand)]
+  command: RawCommand,
+}
+
+impl Raw {
+  async fn run(self) -> Result<(), AnyhowError> {
+    let client = Client::new(self.port)?;
+
+    match self.command {
+      RawCommand::Request(request_command) => client
+        .send_raw_request(&request_command)
+        .await?
+        .response
+        .to_json_str()?
+        .println()
+        .ok(),
+      RawCommand::Notify(notify_command) => client.send_raw_notification(&notify_command).await?.ok(),
+    }
+  }
+}
+
+#[derive(Subcommand)]
+enum RpcCommand {
+  Call(LeanRpcCallCommand),
+  Release(LeanRpcReleaseCommand),
+}
+
+#[derive(Args)]
+struct Rpc {
+  #[arg(long, default_value_t = Server::DEFAULT_PORT)]
+  port: u16,
+  #[command(subcommand)]
+  command: RpcCommand,
+}
+
+impl Rpc {
+  async fn run(self) -> Result<(), AnyhowError> {
+    let client = Client::new(self.port)?;
+
+    match self.command {
+      RpcCommand::Call(call_command) => client
+        .call_lean_rpc(&call_command)
+        .await?
+        .result
+        .to_json_str()?
+        .println()
+        .ok(),
+      RpcCommand::Release(release_command) => client.release_lean_rpc_references(&release_command).await?.ok(),
+    }
+  }
+}
+
+#[derive(Args)]
+struct Symbols {
+  #[arg(long, default_value_t = Server::DEFAULT_PORT)]
+  port: u16,
+  #[command(flatten)]
+  command: GetWorkspaceSymbolsCommand,
+}
+
+impl Symbols {
+  async fn run(self) -> Result<(), AnyhowError> {
+    Client::new(self.port)?
+      .get_workspace_symbols(&self.command)
+      .await?
+      .to_json_str()?
+      .println()
+      .ok()
+  }
+}
+
+#[derive(Subcommand)]
+enum ApplyCommand {
+  Edit(ApplyWorkspaceEditCommand),
+  CodeAction(ApplyCodeActionCommand),
+}
+
+#[derive(Args)]
+struct Apply {
+  #[arg(long, default_value_t = Server::DEFAULT_PORT)]
+  port: u16,
+  #[command(subcommand)]
+  command: ApplyCommand,
+}
+
+impl Apply {
+  async fn run(self) -> Result<(), AnyhowError> {
+    let client = Client::new(self.port)?;
+    let apply_workspace_edit_response = match self.command {
+      ApplyCommand::Edit(edit_command) => client.apply_workspace_edit(&edit_command).await?,
+      ApplyCommand::CodeAction(code_action_command) => client.apply_code_action(&code_action_command).await?,
+    };
+
+    for file_diff in apply_workspace_edit_response.diffs {
+      file_diff.diff.println();
+    }
+
+    ().ok()
+  }
+}
+
 #[derive(Args)]
 struct Serve {
   #[arg(long, default_value_t = Server::DEFAULT_PORT)]
@@ -136,6 +473,8 @@ impl Serve {
 #[derive(Subcommand)]
 enum InfoViewCommand {
   GetPlainGoals(Location),
+  GetPlainTermGoal(Location),
+  GetInteractiveGoals(GetInteractiveGoalsCommand),
 }
 
 #[derive(Args)]
@@ -159,6 +498,18 @@ impl InfoView {
         .to_json_str()?
         .println()
         .ok(),
+      InfoViewCommand::GetPlainTermGoal(command) => Client::new(self.port)?
+        .get_plain_term_goal(self.session_id, command)
+        .await?
+        .to_json_str()?
+        .println()
+        .ok(),
+      InfoViewCommand::GetInteractiveGoals(command) => Client::new(self.port)?
+        .get_interactive_goals(self.session_id, command)
+        .await?
+        .to_json_str()?
+        .println()
+        .ok(),
     }
   }
 }
@@ -195,6 +546,11 @@ enum Command {
   New(New),
   File(File),
   Notifications(Notifications),
+  Reply(Reply),
+  Raw(Raw),
+  Rpc(Rpc),
+  Symbols(Symbols),
+  Apply(Apply),
   Serve(Serve),
   InfoView(InfoView),
   Status(Status),
@@ -245,6 +601,11 @@ impl CliArgs {
       Command::New(new) => new.run().await,
       Command::File(open) => open.run().await,
       Command::Notifications(notifications) => notifications.run().await,
+      Command::Reply(reply) => reply.run().await,
+      Command::Raw(raw) => raw.run().await,
+      Command::Rpc(rpc) => rpc.run().await,
+      Command::Symbols(symbols) => symbols.run().await,
+      Command::Apply(apply) => apply.run().await,
       Command::Serve(serve) => serve.run().await,
       Command::InfoView(info_view) => info_view.run().await,
       Command::Status(status) => status.run().await,
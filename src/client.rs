@@ -7,11 +7,28 @@ use serde_json::Value as Json;
 use ulid::Ulid;
 
 use crate::{
-  commands::{ChangeFileCommand, CloseFileCommand, HoverFileCommand, NewSessionCommand, OpenFileCommand},
+  commands::{
+    ApplyCodeActionCommand, ApplyWorkspaceEditCommand, ChangeFileCommand, CloseFileCommand, EditFileCommand,
+    ExpandTraceChildrenCommand, GetCallHierarchyCommand, GetCodeActionsCommand, GetCompletionsCommand,
+    GetDiagnosticsCommand, GetDocumentHighlightsCommand, GetDocumentSymbolsCommand, GetFileCommand,
+    GetFoldingRangesCommand, GetInlayHintsCommand, GetInteractiveDiagnosticsCommand, GetInteractiveGoalsCommand,
+    GetReferencesCommand, GetSemanticTokensCommand, GetSemanticTokensRangeCommand, GetWorkspaceSymbolsCommand,
+    GoToCommand, HoverFileCommand, LeanRpcCallCommand, LeanRpcReleaseCommand, NewSessionCommand, OpenFileCommand,
+    PrepareRenameCommand, RawMessageCommand, RenameCommand, ReplyToServerRequestCommand, ResolveCompletionItemCommand,
+    SaveFileCommand, WaitForFileCommand,
+  },
   server::{
     Server,
-    requests::ChangeFileRequest,
-    responses::{GetPlainGoalsResponse, GetSessionsResponse, HoverFileResponse, NewSessionResponse},
+    requests::{ChangeFileRequest, EditFileRequest},
+    responses::{
+      ApplyWorkspaceEditResponse, ExpandTraceChildrenResponse, GetCallHierarchyResponse, GetCodeActionsResponse,
+      GetCompletionsResponse, GetDiagnosticsResponse, GetDocumentHighlightsResponse, GetDocumentSymbolsResponse,
+      GetFileResponse, GetFoldingRangesResponse, GetInlayHintsResponse, GetInteractiveDiagnosticsResponse,
+      GetInteractiveGoalsResponse, GetLocationsResponse, GetPlainGoalsResponse, GetPlainTermGoalResponse,
+      GetSemanticTokensResponse, GetSessionsResponse, GetWorkspaceSymbolsResponse, HoverFileResponse,
+      LeanRpcCallResponse, NewSessionResponse, OpenFileResponse, PrepareRenameResponse, RawRequestResponse,
+      RenameResponse, ResolveCompletionItemResponse,
+    },
   },
   types::{Location, SessionSetStatus},
 };
@@ -53,7 +70,7 @@ impl Client {
       .ok()
   }
 
-  pub async fn open_file(&self, command: &OpenFileCommand) -> Result<(), AnyhowError> {
+  pub async fn open_file(&self, command: &OpenFileCommand) -> Result<OpenFileResponse, AnyhowError> {
     let url = self.url(Server::PATH_FILE_OPEN);
 
     self
@@ -64,7 +81,7 @@ impl Client {
       .await?
       .check_status()
       .await?
-      .json::<()>()
+      .json::<OpenFileResponse>()
       .await?
       .ok()
   }
@@ -86,6 +103,55 @@ impl Client {
       .ok()
   }
 
+  pub async fn edit_file(&self, command: EditFileCommand) -> Result<(), AnyhowError> {
+    let url = self.url(Server::PATH_FILE_EDIT);
+    let request = EditFileRequest::new(command).await?;
+
+    self
+      .http_client
+      .post(url)
+      .json(&request)
+      .send()
+      .await?
+      .check_status()
+      .await?
+      .json::<()>()
+      .await?
+      .ok()
+  }
+
+  pub async fn get_file(&self, command: &GetFileCommand) -> Result<GetFileResponse, AnyhowError> {
+    let url = self.url(Server::PATH_FILE_GET);
+
+    self
+      .http_client
+      .post(url)
+      .json(command)
+      .send()
+      .await?
+      .check_status()
+      .await?
+      .json::<GetFileResponse>()
+      .await?
+      .ok()
+  }
+
+  pub async fn save_file(&self, command: &SaveFileCommand) -> Result<(), AnyhowError> {
+    let url = self.url(Server::PATH_FILE_SAVE);
+
+    self
+      .http_client
+      .post(url)
+      .json(command)
+      .send()
+      .await?
+      .check_status()
+      .await?
+      .json::<()>()
+      .await?
+      .ok()
+  }
+
   pub async fn close_file(&self, command: &CloseFileCommand) -> Result<(), AnyhowError> {
     let url = self.url(Server::PATH_FILE_CLOSE);
 
@@ -118,6 +184,261 @@ impl Client {
       .ok()
   }
 
+  pub async fn get_definition(&self, command: &GoToCommand) -> Result<GetLocationsResponse, AnyhowError> {
+    let url = self.url(Server::PATH_FILE_DEFINITION);
+
+    self
+      .http_client
+      .post(url)
+      .json(command)
+      .send()
+      .await?
+      .check_status()
+      .await?
+      .json::<GetLocationsResponse>()
+      .await?
+      .ok()
+  }
+
+  pub async fn get_declaration(&self, command: &GoToCommand) -> Result<GetLocationsResponse, AnyhowError> {
+    let url = self.url(Server::PATH_FILE_DECLARATION);
+
+    self
+      .http_client
+      .post(url)
+      .json(command)
+      .send()
+      .await?
+      .check_status()
+      .await?
+      .json::<GetLocationsResponse>()
+      .await?
+      .ok()
+  }
+
+  pub async fn get_type_definition(&self, command: &GoToCommand) -> Result<GetLocationsResponse, AnyhowError> {
+    let url = self.url(Server::PATH_FILE_TYPE_DEFINITION);
+
+    self
+      .http_client
+      .post(url)
+      .json(command)
+      .send()
+      .await?
+      .check_status()
+      .await?
+      .json::<GetLocationsResponse>()
+      .await?
+      .ok()
+  }
+
+  pub async fn get_references(&self, command: &GetReferencesCommand) -> Result<GetLocationsResponse, AnyhowError> {
+    let url = self.url(Server::PATH_FILE_REFERENCES);
+
+    self
+      .http_client
+      .post(url)
+      .json(command)
+      .send()
+      .await?
+      .check_status()
+      .await?
+      .json::<GetLocationsResponse>()
+      .await?
+      .ok()
+  }
+
+  pub async fn prepare_rename(&self, command: &PrepareRenameCommand) -> Result<PrepareRenameResponse, AnyhowError> {
+    let url = self.url(Server::PATH_FILE_PREPARE_RENAME);
+
+    self
+      .http_client
+      .post(url)
+      .json(command)
+      .send()
+      .await?
+      .check_status()
+      .await?
+      .json::<PrepareRenameResponse>()
+      .await?
+      .ok()
+  }
+
+  pub async fn rename(&self, command: &RenameCommand) -> Result<RenameResponse, AnyhowError> {
+    let url = self.url(Server::PATH_FILE_RENAME);
+
+    self
+      .http_client
+      .post(url)
+      .json(command)
+      .send()
+      .await?
+      .check_status()
+      .await?
+      .json::<RenameResponse>()
+      .await?
+      .ok()
+  }
+
+  pub async fn get_completions(&self, command: &GetCompletionsCommand) -> Result<GetCompletionsResponse, AnyhowError> {
+    let url = self.url(Server::PATH_FILE_COMPLETION);
+
+    self
+      .http_client
+      .post(url)
+      .json(command)
+      .send()
+      .await?
+      .check_status()
+      .await?
+      .json::<GetCompletionsResponse>()
+      .await?
+      .ok()
+  }
+
+  pub async fn resolve_completion_item(
+    &self,
+    command: &ResolveCompletionItemCommand,
+  ) -> Result<ResolveCompletionItemResponse, AnyhowError> {
+    let url = self.url(Server::PATH_COMPLETION_RESOLVE);
+
+    self
+      .http_client
+      .post(url)
+      .json(command)
+      .send()
+      .await?
+      .check_status()
+      .await?
+      .json::<ResolveCompletionItemResponse>()
+      .await?
+      .ok()
+  }
+
+  pub async fn get_document_symbols(
+    &self,
+    command: &GetDocumentSymbolsCommand,
+  ) -> Result<GetDocumentSymbolsResponse, AnyhowError> {
+    let url = self.url(Server::PATH_FILE_DOCUMENT_SYMBOLS);
+
+    self
+      .http_client
+      .post(url)
+      .json(command)
+      .send()
+      .await?
+      .check_status()
+      .await?
+      .json::<GetDocumentSymbolsResponse>()
+      .await?
+      .ok()
+  }
+
+  pub async fn get_code_actions(&self, command: &GetCodeActionsCommand) -> Result<GetCodeActionsResponse, AnyhowError> {
+    let url = self.url(Server::PATH_FILE_CODE_ACTIONS);
+
+    self
+      .http_client
+      .post(url)
+      .json(command)
+      .send()
+      .await?
+      .check_status()
+      .await?
+      .json::<GetCodeActionsResponse>()
+      .await?
+      .ok()
+  }
+
+  pub async fn get_folding_ranges(
+    &self,
+    command: &GetFoldingRangesCommand,
+  ) -> Result<GetFoldingRangesResponse, AnyhowError> {
+    let url = self.url(Server::PATH_FILE_FOLDING_RANGES);
+
+    self
+      .http_client
+      .post(url)
+      .json(command)
+      .send()
+      .await?
+      .check_status()
+      .await?
+      .json::<GetFoldingRangesResponse>()
+      .await?
+      .ok()
+  }
+
+  pub async fn get_diagnostics(&self, command: &GetDiagnosticsCommand) -> Result<GetDiagnosticsResponse, AnyhowError> {
+    let url = self.url(Server::PATH_FILE_DIAGNOSTICS);
+
+    self
+      .http_client
+      .post(url)
+      .json(command)
+      .send()
+      .await?
+      .check_status()
+      .await?
+      .json::<GetDiagnosticsResponse>()
+      .await?
+      .ok()
+  }
+
+  pub async fn get_interactive_diagnostics(
+    &self,
+    command: &GetInteractiveDiagnosticsCommand,
+  ) -> Result<GetInteractiveDiagnosticsResponse, AnyhowError> {
+    let url = self.url(Server::PATH_FILE_INTERACTIVE_DIAGNOSTICS);
+
+    self
+      .http_client
+      .post(url)
+      .json(command)
+      .send()
+      .await?
+      .check_status()
+      .await?
+      .json::<GetInteractiveDiagnosticsResponse>()
+      .await?
+      .ok()
+  }
+
+  pub async fn expand_trace_children(
+    &self,
+    command: &ExpandTraceChildrenCommand,
+  ) -> Result<ExpandTraceChildrenResponse, AnyhowError> {
+    let url = self.url(Server::PATH_FILE_TRACE_CHILDREN);
+
+    self
+      .http_client
+      .post(url)
+      .json(command)
+      .send()
+      .await?
+      .check_status()
+      .await?
+      .json::<ExpandTraceChildrenResponse>()
+      .await?
+      .ok()
+  }
+
+  pub async fn wait_for_file(&self, command: &WaitForFileCommand) -> Result<GetDiagnosticsResponse, AnyhowError> {
+    let url = self.url(Server::PATH_FILE_WAIT);
+
+    self
+      .http_client
+      .post(url)
+      .json(command)
+      .send()
+      .await?
+      .check_status()
+      .await?
+      .json::<GetDiagnosticsResponse>()
+      .await?
+      .ok()
+  }
+
   pub async fn get(&self, session_id: Option<Ulid>) -> Result<GetSessionsResponse, AnyhowError> {
     let url = self.url(Server::PATH_GET_SESSIONS);
 
@@ -181,6 +502,282 @@ impl Client {
       .ok()
   }
 
+  pub async fn get_plain_term_goal(
+    &self,
+    session_id: Option<Ulid>,
+    location: Location,
+  ) -> Result<GetPlainTermGoalResponse, AnyhowError> {
+    let url = self.url(Server::PATH_GET_PLAIN_TERM_GOAL);
+
+    self
+      .http_client
+      .get(url)
+      .query_one::<Ulid>(Server::QUERY_PARAM_SESSION_ID, session_id)
+      .query_one(Server::QUERY_PARAM_FILEPATH, location.filepath)
+      .query_one(Server::QUERY_PARAM_LINE, location.line)
+      .query_one(Server::QUERY_PARAM_CHARACTER, location.character)
+      .send()
+      .await?
+      .check_status()
+      .await?
+      .json::<GetPlainTermGoalResponse>()
+      .await?
+      .ok()
+  }
+
+  pub async fn get_interactive_goals(
+    &self,
+    session_id: Option<Ulid>,
+    command: GetInteractiveGoalsCommand,
+  ) -> Result<GetInteractiveGoalsResponse, AnyhowError> {
+    let url = self.url(Server::PATH_GET_INTERACTIVE_GOALS);
+
+    self
+      .http_client
+      .get(url)
+      .query_one::<Ulid>(Server::QUERY_PARAM_SESSION_ID, session_id)
+      .query_one(Server::QUERY_PARAM_FILEPATH, command.location.filepath)
+      .query_one(Server::QUERY_PARAM_LINE, command.location.line)
+      .query_one(Server::QUERY_PARAM_CHARACTER, command.location.character)
+      .query_one(Server::QUERY_PARAM_KEEP_REFERENCES, command.keep_references)
+      .send()
+      .await?
+      .check_status()
+      .await?
+      .json::<GetInteractiveGoalsResponse>()
+      .await?
+      .ok()
+  }
+
+  pub async fn reply_to_server_request(&self, command: &ReplyToServerRequestCommand) -> Result<(), AnyhowError> {
+    let url = self.url(Server::PATH_REPLY_TO_SERVER_REQUEST);
+
+    self
+      .http_client
+      .post(url)
+      .json(command)
+      .send()
+      .await?
+      .check_status()
+      .await?
+      .json::<()>()
+      .await?
+      .ok()
+  }
+
+  pub async fn get_call_hierarchy(
+    &self,
+    command: &GetCallHierarchyCommand,
+  ) -> Result<GetCallHierarchyResponse, AnyhowError> {
+    let url = self.url(Server::PATH_FILE_CALL_HIERARCHY);
+
+    self
+      .http_client
+      .post(url)
+      .json(command)
+      .send()
+      .await?
+      .check_status()
+      .await?
+      .json::<GetCallHierarchyResponse>()
+      .await?
+      .ok()
+  }
+
+  pub async fn get_inlay_hints(&self, command: &GetInlayHintsCommand) -> Result<GetInlayHintsResponse, AnyhowError> {
+    let url = self.url(Server::PATH_FILE_INLAY_HINTS);
+
+    self
+      .http_client
+      .post(url)
+      .json(command)
+      .send()
+      .await?
+      .check_status()
+      .await?
+      .json::<GetInlayHintsResponse>()
+      .await?
+      .ok()
+  }
+
+  pub async fn get_document_highlights(
+    &self,
+    command: &GetDocumentHighlightsCommand,
+  ) -> Result<GetDocumentHighlightsResponse, AnyhowError> {
+    let url = self.url(Server::PATH_FILE_DOCUMENT_HIGHLIGHTS);
+
+    self
+      .http_client
+      .post(url)
+      .json(command)
+      .send()
+      .await?
+      .check_status()
+      .await?
+      .json::<GetDocumentHighlightsResponse>()
+      .await?
+      .ok()
+  }
+
+  pub async fn get_workspace_symbols(
+    &self,
+    command: &GetWorkspaceSymbolsCommand,
+  ) -> Result<GetWorkspaceSymbolsResponse, AnyhowError> {
+    let url = self.url(Server::PATH_WORKSPACE_SYMBOLS);
+
+    self
+      .http_client
+      .post(url)
+      .json(command)
+      .send()
+      .await?
+      .check_status()
+      .await?
+      .json::<GetWorkspaceSymbolsResponse>()
+      .await?
+      .ok()
+  }
+
+  pub async fn get_semantic_tokens(
+    &self,
+    command: &GetSemanticTokensCommand,
+  ) -> Result<GetSemanticTokensResponse, AnyhowError> {
+    let url = self.url(Server::PATH_FILE_SEMANTIC_TOKENS);
+
+    self
+      .http_client
+      .post(url)
+      .json(command)
+      .send()
+      .await?
+      .check_status()
+      .await?
+      .json::<GetSemanticTokensResponse>()
+      .await?
+      .ok()
+  }
+
+  pub async fn get_semantic_tokens_range(
+    &self,
+    command: &GetSemanticTokensRangeCommand,
+  ) -> Result<GetSemanticTokensResponse, AnyhowError> {
+    let url = self.url(Server::PATH_FILE_SEMANTIC_TOKENS_RANGE);
+
+    self
+      .http_client
+      .post(url)
+      .json(command)
+      .send()
+      .await?
+      .check_status()
+      .await?
+      .json::<GetSemanticTokensResponse>()
+      .await?
+      .ok()
+  }
+
+  pub async fn apply_workspace_edit(
+    &self,
+    command: &ApplyWorkspaceEditCommand,
+  ) -> Result<ApplyWorkspaceEditResponse, AnyhowError> {
+    let url = self.url(Server::PATH_APPLY_WORKSPACE_EDIT);
+
+    self
+      .http_client
+      .post(url)
+      .json(command)
+      .send()
+      .await?
+      .check_status()
+      .await?
+      .json::<ApplyWorkspaceEditResponse>()
+      .await?
+      .ok()
+  }
+
+  pub async fn apply_code_action(
+    &self,
+    command: &ApplyCodeActionCommand,
+  ) -> Result<ApplyWorkspaceEditResponse, AnyhowError> {
+    let url = self.url(Server::PATH_APPLY_CODE_ACTION);
+
+    self
+      .http_client
+      .post(url)
+      .json(command)
+      .send()
+      .await?
+      .check_status()
+      .await?
+      .json::<ApplyWorkspaceEditResponse>()
+      .await?
+      .ok()
+  }
+
+  pub async fn call_lean_rpc(&self, command: &LeanRpcCallCommand) -> Result<LeanRpcCallResponse, AnyhowError> {
+    let url = self.url(Server::PATH_LEAN_RPC_CALL);
+
+    self
+      .http_client
+      .post(url)
+      .json(command)
+      .send()
+      .await?
+      .check_status()
+      .await?
+      .json::<LeanRpcCallResponse>()
+      .await?
+      .ok()
+  }
+
+  pub async fn release_lean_rpc_references(&self, command: &LeanRpcReleaseCommand) -> Result<(), AnyhowError> {
+    let url = self.url(Server::PATH_LEAN_RPC_RELEASE);
+
+    self
+      .http_client
+      .post(url)
+      .json(command)
+      .send()
+      .await?
+      .check_status()
+      .await?
+      .json::<()>()
+      .await?
+      .ok()
+  }
+
+  pub async fn send_raw_request(&self, command: &RawMessageCommand) -> Result<RawRequestResponse, AnyhowError> {
+    let url = self.url(Server::PATH_LSP_REQUEST);
+
+    self
+      .http_client
+      .post(url)
+      .json(command)
+      .send()
+      .await?
+      .check_status()
+      .await?
+      .json::<RawRequestResponse>()
+      .await?
+      .ok()
+  }
+
+  pub async fn send_raw_notification(&self, command: &RawMessageCommand) -> Result<(), AnyhowError> {
+    let url = self.url(Server::PATH_LSP_NOTIFY);
+
+    self
+      .http_client
+      .post(url)
+      .json(command)
+      .send()
+      .await?
+      .check_status()
+      .await?
+      .json::<()>()
+      .await?
+      .ok()
+  }
+
   pub async fn status(&self) -> Result<SessionSetStatus, AnyhowError> {
     let url = self.url(Server::PATH_GET_SESSION_SET_STATUS);
 
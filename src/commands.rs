@@ -5,40 +5,216 @@ use clap::Args;
 use derive_more::Constructor;
 use poem_openapi::Object;
 use serde::{Deserialize, Serialize};
+use serde_json::Value as Json;
 use tokio::sync::oneshot::Sender as OneshotSender;
 use ulid::Ulid;
 
 use crate::{
-  lean_server::LeanServer,
-  server::responses::{GetPlainGoalsResponse, HoverFileResponse},
+  lean_server::{LeanServer, LeanServerCommand},
+  server::responses::{
+    ApplyWorkspaceEditResponse, ExpandTraceChildrenResponse, GetCodeActionsResponse, GetCompletionsResponse,
+    GetDiagnosticsResponse, GetDocumentHighlightsResponse, GetDocumentSymbolsResponse, GetFileResponse,
+    GetFoldingRangesResponse, GetInlayHintsResponse, GetInteractiveDiagnosticsResponse, GetInteractiveGoalsResponse,
+    GetLocationsResponse, GetPlainGoalsResponse, GetPlainTermGoalResponse, GetSemanticTokensResponse,
+    GetWorkspaceSymbolsResponse, HoverFileResponse, LeanRpcCallResponse, OpenFileResponse, PrepareRenameResponse,
+    RawRequestResponse, RenameResponse, ResolveCompletionItemResponse,
+  },
   session::Session,
-  types::{Location, SessionStatus},
+  types::{
+    CallHierarchyCall, DiagnosticFilter, EnvVar, Location, RangeLocation, RequestTimeout, ServerRequestReply,
+    SessionStatus, TextEdit,
+  },
 };
 
 pub enum SessionCommand {
   Initialize {
-    sender: OneshotSender<()>,
+    sender: OneshotSender<Result<(), AnyhowError>>,
   },
   OpenFile {
-    sender: OneshotSender<Result<(), AnyhowError>>,
-    filepath: PathBuf,
+    sender: OneshotSender<Result<OpenFileResponse, AnyhowError>>,
+    filepath: Option<PathBuf>,
+    text: Option<String>,
   },
   ChangeFile {
     sender: OneshotSender<Result<(), AnyhowError>>,
     filepath: PathBuf,
     text: String,
   },
+  EditFile {
+    sender: OneshotSender<Result<(), AnyhowError>>,
+    filepath: PathBuf,
+    edits: Vec<TextEdit>,
+  },
+  GetFile {
+    sender: OneshotSender<Result<GetFileResponse, AnyhowError>>,
+    filepath: PathBuf,
+  },
+  SaveFile {
+    sender: OneshotSender<Result<(), AnyhowError>>,
+    filepath: PathBuf,
+  },
   CloseFile {
     sender: OneshotSender<Result<(), AnyhowError>>,
     filepath: PathBuf,
   },
   HoverFile {
-    sender: OneshotSender<HoverFileResponse>,
+    sender: OneshotSender<Result<HoverFileResponse, AnyhowError>>,
+    location: Location,
+  },
+  GetDefinition {
+    sender: OneshotSender<Result<GetLocationsResponse, AnyhowError>>,
+    location: Location,
+  },
+  GetDeclaration {
+    sender: OneshotSender<Result<GetLocationsResponse, AnyhowError>>,
+    location: Location,
+  },
+  GetTypeDefinition {
+    sender: OneshotSender<Result<GetLocationsResponse, AnyhowError>>,
+    location: Location,
+  },
+  GetReferences {
+    sender: OneshotSender<Result<GetLocationsResponse, AnyhowError>>,
+    location: Location,
+    include_declaration: bool,
+  },
+  PrepareRename {
+    sender: OneshotSender<Result<PrepareRenameResponse, AnyhowError>>,
+    location: Location,
+  },
+  Rename {
+    sender: OneshotSender<Result<RenameResponse, AnyhowError>>,
+    location: Location,
+    new_name: String,
+  },
+  GetCompletions {
+    sender: OneshotSender<Result<GetCompletionsResponse, AnyhowError>>,
+    location: Location,
+    trigger_kind: Option<usize>,
+    trigger_character: Option<String>,
+  },
+  ResolveCompletionItem {
+    sender: OneshotSender<Result<ResolveCompletionItemResponse, AnyhowError>>,
+    item: Json,
+  },
+  GetDocumentSymbols {
+    sender: OneshotSender<Result<GetDocumentSymbolsResponse, AnyhowError>>,
+    filepath: PathBuf,
+  },
+  GetCodeActions {
+    sender: OneshotSender<Result<GetCodeActionsResponse, AnyhowError>>,
+    range_location: RangeLocation,
+  },
+  GetFoldingRanges {
+    sender: OneshotSender<Result<GetFoldingRangesResponse, AnyhowError>>,
+    filepath: PathBuf,
+  },
+  PrepareCallHierarchy {
+    sender: OneshotSender<Result<Vec<CallHierarchyCall>, AnyhowError>>,
     location: Location,
   },
+  GetCallHierarchyCalls {
+    sender: OneshotSender<Result<Vec<CallHierarchyCall>, AnyhowError>>,
+    item: Json,
+    outgoing: bool,
+  },
+  GetInlayHints {
+    sender: OneshotSender<Result<GetInlayHintsResponse, AnyhowError>>,
+    range_location: RangeLocation,
+  },
+  GetDocumentHighlights {
+    sender: OneshotSender<Result<GetDocumentHighlightsResponse, AnyhowError>>,
+    location: Location,
+  },
+  GetWorkspaceSymbols {
+    sender: OneshotSender<Result<GetWorkspaceSymbolsResponse, AnyhowError>>,
+    query: String,
+    limit: Option<usize>,
+    kinds: Vec<usize>,
+  },
+  GetSemanticTokens {
+    sender: OneshotSender<Result<GetSemanticTokensResponse, AnyhowError>>,
+    filepath: PathBuf,
+  },
+  GetSemanticTokensRange {
+    sender: OneshotSender<Result<GetSemanticTokensResponse, AnyhowError>>,
+    range_location: RangeLocation,
+  },
+  ApplyWorkspaceEdit {
+    sender: OneshotSender<Result<ApplyWorkspaceEditResponse, AnyhowError>>,
+    workspace_edit: Json,
+    write: bool,
+  },
+  ApplyCodeAction {
+    sender: OneshotSender<Result<ApplyWorkspaceEditResponse, AnyhowError>>,
+    action: Json,
+    write: bool,
+  },
   GetPlainGoals {
-    sender: OneshotSender<GetPlainGoalsResponse>,
+    sender: OneshotSender<Result<GetPlainGoalsResponse, AnyhowError>>,
+    location: Location,
+  },
+  GetPlainTermGoal {
+    sender: OneshotSender<Result<GetPlainTermGoalResponse, AnyhowError>>,
+    location: Location,
+  },
+  GetInteractiveGoals {
+    sender: OneshotSender<Result<GetInteractiveGoalsResponse, AnyhowError>>,
+    location: Location,
+    keep_references: bool,
+  },
+  GetDiagnostics {
+    sender: OneshotSender<Result<GetDiagnosticsResponse, AnyhowError>>,
+    filepath: PathBuf,
+    filter: DiagnosticFilter,
+  },
+  GetInteractiveDiagnostics {
+    sender: OneshotSender<Result<GetInteractiveDiagnosticsResponse, AnyhowError>>,
+    filepath: PathBuf,
+    start_line: Option<usize>,
+    end_line: Option<usize>,
+    keep_references: bool,
+  },
+  ExpandTraceChildren {
+    sender: OneshotSender<Result<ExpandTraceChildrenResponse, AnyhowError>>,
+    filepath: PathBuf,
+    children: Json,
+    keep_references: bool,
+  },
+  WaitForFile {
+    sender: OneshotSender<Result<GetDiagnosticsResponse, AnyhowError>>,
+    filepath: PathBuf,
+    version: Option<usize>,
+    timeout_ms: Option<u64>,
+    filter: DiagnosticFilter,
+  },
+  CallLeanRpc {
+    sender: OneshotSender<Result<LeanRpcCallResponse, AnyhowError>>,
     location: Location,
+    method: String,
+    params: Option<Json>,
+    keep_references: bool,
+  },
+  ReleaseLeanRpcReferences {
+    sender: OneshotSender<Result<(), AnyhowError>>,
+    filepath: PathBuf,
+    refs: Vec<Json>,
+  },
+  ReplyToServerRequest {
+    sender: OneshotSender<Result<(), AnyhowError>>,
+    id: Json,
+    result: Option<Json>,
+    error: Option<Json>,
+  },
+  SendRawRequest {
+    sender: OneshotSender<Result<RawRequestResponse, AnyhowError>>,
+    method: String,
+    params: Option<Json>,
+  },
+  SendRawNotification {
+    sender: OneshotSender<Result<(), AnyhowError>>,
+    method: String,
+    params: Option<Json>,
   },
   GetStatus {
     sender: OneshotSender<SessionStatus>,
@@ -55,18 +231,85 @@ pub struct NewSessionCommand {
 
   #[arg(long = "log-dir", env = Self::LEAN_SERVER_LOG_DIRPATH_ENV_NAME)]
   pub lean_server_log_dirpath: Option<PathBuf>,
+
+  /// Program to launch the lean server with instead of `lake serve`
+  #[arg(long = "server-command")]
+  pub lean_server_command: Option<String>,
+
+  /// Argument to pass to the lean server program (appended to `serve` when no
+  /// command is given)
+  #[arg(long = "server-arg", allow_hyphen_values = true)]
+  #[oai(default)]
+  #[serde(default)]
+  pub lean_server_args: Vec<String>,
+
+  /// Environment variable to set for the lean server process, given as
+  /// NAME=VALUE
+  #[arg(long = "server-env")]
+  #[oai(default)]
+  #[serde(default)]
+  pub lean_server_env: Vec<EnvVar>,
+
+  /// Number of times to restart the lean server after it exits before giving up
+  /// on the session
+  #[arg(long = "max-restarts")]
+  pub lean_server_max_restarts: Option<usize>,
+
+  /// Timeout for requests to the lean server, given as [METHOD=]MILLISECONDS;
+  /// requests for methods without a timeout use the one without a method, if
+  /// any
+  #[arg(long = "request-timeout")]
+  #[oai(default)]
+  #[serde(default)]
+  pub request_timeouts: Vec<RequestTimeout>,
+
+  /// Result to reply with to requests from the lean server, given as
+  /// METHOD=JSON; overrides the default reply
+  #[arg(long = "server-request-reply")]
+  #[oai(default)]
+  #[serde(default)]
+  pub server_request_replies: Vec<ServerRequestReply>,
+
+  /// Forward changes made on disk to open files to the lean server as edits
+  #[arg(long)]
+  #[oai(default)]
+  #[serde(default)]
+  pub auto_reload: bool,
 }
 
 impl NewSessionCommand {
   const DEFAULT_LEAN_PATH_STR: &'static str = ".";
   const LEAN_SERVER_LOG_DIRPATH_ENV_NAME: &'static str = LeanServer::LOG_DIRPATH_ENV_NAME;
+
+  pub fn lean_server_command(&self) -> LeanServerCommand {
+    let (program, args) = match &self.lean_server_command {
+      Some(program) => (program.clone(), self.lean_server_args.clone()),
+      None => {
+        let args = LeanServer::DEFAULT_ARGS
+          .iter()
+          .map(ToString::to_string)
+          .chain(self.lean_server_args.iter().cloned())
+          .collect();
+
+        (LeanServer::DEFAULT_PROGRAM.to_owned(), args)
+      }
+    };
+    let env = self.lean_server_env.clone();
+
+    LeanServerCommand { program, args, env }
+  }
 }
 
 #[derive(Args, Constructor, Deserialize, Object, Serialize)]
 pub struct OpenFileCommand {
   #[arg(long)]
   pub session_id: Option<Ulid>,
-  pub lean_filepath: PathBuf,
+  /// Path of the file to open; when text is given, this is the virtual path of
+  /// the document and defaults to a generated path inside the project
+  pub lean_filepath: Option<PathBuf>,
+  /// Text to open the document with instead of reading it from disk
+  #[arg(long)]
+  pub text: Option<String>,
 }
 
 #[derive(Args)]
@@ -78,6 +321,30 @@ pub struct ChangeFileCommand {
   pub input_filepath: Option<PathBuf>,
 }
 
+#[derive(Args)]
+pub struct EditFileCommand {
+  #[arg(long)]
+  pub session_id: Option<Ulid>,
+  pub lean_filepath: PathBuf,
+  /// File containing a json array of edits to apply in order; defaults to stdin
+  #[arg(long)]
+  pub input_filepath: Option<PathBuf>,
+}
+
+#[derive(Args, Constructor, Deserialize, Object, Serialize)]
+pub struct GetFileCommand {
+  #[arg(long)]
+  pub session_id: Option<Ulid>,
+  pub lean_filepath: PathBuf,
+}
+
+#[derive(Args, Constructor, Deserialize, Object, Serialize)]
+pub struct SaveFileCommand {
+  #[arg(long)]
+  pub session_id: Option<Ulid>,
+  pub lean_filepath: PathBuf,
+}
+
 #[derive(Args, Constructor, Deserialize, Object, Serialize)]
 pub struct CloseFileCommand {
   #[arg(long)]
@@ -93,6 +360,287 @@ pub struct HoverFileCommand {
   pub location: Location,
 }
 
+#[derive(Args, Constructor, Deserialize, Object, Serialize)]
+pub struct GoToCommand {
+  #[arg(long)]
+  pub session_id: Option<Ulid>,
+  #[command(flatten)]
+  pub location: Location,
+}
+
+#[derive(Args, Constructor, Deserialize, Object, Serialize)]
+pub struct GetReferencesCommand {
+  #[arg(long)]
+  pub session_id: Option<Ulid>,
+  #[command(flatten)]
+  pub location: Location,
+  #[arg(long)]
+  #[oai(default)]
+  #[serde(default)]
+  pub include_declaration: bool,
+}
+
+#[derive(Args, Constructor, Deserialize, Object, Serialize)]
+pub struct PrepareRenameCommand {
+  #[arg(long)]
+  pub session_id: Option<Ulid>,
+  #[command(flatten)]
+  pub location: Location,
+}
+
+#[derive(Args, Constructor, Deserialize, Object, Serialize)]
+pub struct RenameCommand {
+  #[arg(long)]
+  pub session_id: Option<Ulid>,
+  #[command(flatten)]
+  pub location: Location,
+  #[arg(long)]
+  pub new_name: String,
+}
+
+#[derive(Args, Constructor, Deserialize, Object, Serialize)]
+pub struct GetCompletionsCommand {
+  #[arg(long)]
+  pub session_id: Option<Ulid>,
+  #[command(flatten)]
+  pub location: Location,
+  /// How completion was triggered: 1 (invoked), 2 (trigger character) or 3
+  /// (re-triggered for incomplete completions); defaults to 2 when a trigger
+  /// character is given and 1 otherwise
+  #[arg(long)]
+  pub trigger_kind: Option<usize>,
+  #[arg(long)]
+  pub trigger_character: Option<String>,
+}
+
+#[derive(Args, Deserialize, Object, Serialize)]
+pub struct ResolveCompletionItemCommand {
+  #[arg(long)]
+  pub session_id: Option<Ulid>,
+  pub item: Json,
+}
+
+#[derive(Args, Constructor, Deserialize, Object, Serialize)]
+pub struct GetDocumentSymbolsCommand {
+  #[arg(long)]
+  pub session_id: Option<Ulid>,
+  pub lean_filepath: PathBuf,
+}
+
+// NOTE: the diagnostics overlapping the range are sent along, so the code
+// actions for a diagnostic can be requested with its range
+#[derive(Args, Constructor, Deserialize, Object, Serialize)]
+pub struct GetCodeActionsCommand {
+  #[arg(long)]
+  pub session_id: Option<Ulid>,
+  #[command(flatten)]
+  pub range_location: RangeLocation,
+}
+
+#[derive(Args, Constructor, Deserialize, Object, Serialize)]
+pub struct GetFoldingRangesCommand {
+  #[arg(long)]
+  pub session_id: Option<Ulid>,
+  pub lean_filepath: PathBuf,
+}
+
+#[derive(Args, Constructor, Deserialize, Object, Serialize)]
+pub struct GetCallHierarchyCommand {
+  #[arg(long)]
+  pub session_id: Option<Ulid>,
+  #[command(flatten)]
+  pub location: Location,
+  /// Resolve the calls made by the item instead of the calls made to it
+  #[arg(long)]
+  #[oai(default)]
+  #[serde(default)]
+  pub outgoing: bool,
+  /// Number of levels of calls to resolve
+  #[arg(long)]
+  pub depth: Option<usize>,
+}
+
+#[derive(Args, Constructor, Deserialize, Object, Serialize)]
+pub struct GetInlayHintsCommand {
+  #[arg(long)]
+  pub session_id: Option<Ulid>,
+  #[command(flatten)]
+  pub range_location: RangeLocation,
+}
+
+#[derive(Args, Constructor, Deserialize, Object, Serialize)]
+pub struct GetDocumentHighlightsCommand {
+  #[arg(long)]
+  pub session_id: Option<Ulid>,
+  #[command(flatten)]
+  pub location: Location,
+}
+
+#[derive(Args, Constructor, Deserialize, Object, Serialize)]
+pub struct GetWorkspaceSymbolsCommand {
+  #[arg(long)]
+  pub session_id: Option<Ulid>,
+  pub query: String,
+  /// Maximum number of symbols to return
+  #[arg(long)]
+  pub limit: Option<usize>,
+  /// Lsp symbol kind to restrict the results to, e.g. 12 for functions; may be
+  /// given multiple times
+  #[arg(long = "kind")]
+  #[oai(default)]
+  #[serde(default)]
+  pub kinds: Vec<usize>,
+}
+
+#[derive(Args, Constructor, Deserialize, Object, Serialize)]
+pub struct GetSemanticTokensCommand {
+  #[arg(long)]
+  pub session_id: Option<Ulid>,
+  pub lean_filepath: PathBuf,
+}
+
+#[derive(Args, Constructor, Deserialize, Object, Serialize)]
+pub struct GetSemanticTokensRangeCommand {
+  #[arg(long)]
+  pub session_id: Option<Ulid>,
+  #[command(flatten)]
+  pub range_location: RangeLocation,
+}
+
+#[derive(Args, Deserialize, Object, Serialize)]
+pub struct ApplyWorkspaceEditCommand {
+  #[arg(long)]
+  pub session_id: Option<Ulid>,
+  pub workspace_edit: Json,
+  #[arg(long)]
+  #[oai(default)]
+  #[serde(default)]
+  pub write: bool,
+}
+
+#[derive(Args, Deserialize, Object, Serialize)]
+pub struct ApplyCodeActionCommand {
+  #[arg(long)]
+  pub session_id: Option<Ulid>,
+  pub action: Json,
+  #[arg(long)]
+  #[oai(default)]
+  #[serde(default)]
+  pub write: bool,
+}
+
+#[derive(Args, Constructor, Deserialize, Object, Serialize)]
+pub struct GetDiagnosticsCommand {
+  #[arg(long)]
+  pub session_id: Option<Ulid>,
+  pub lean_filepath: PathBuf,
+  #[command(flatten)]
+  #[oai(default)]
+  #[serde(default)]
+  pub filter: DiagnosticFilter,
+}
+
+#[derive(Args, Constructor, Deserialize, Object, Serialize)]
+pub struct GetInteractiveDiagnosticsCommand {
+  #[arg(long)]
+  pub session_id: Option<Ulid>,
+  pub lean_filepath: PathBuf,
+  #[arg(long)]
+  pub start_line: Option<usize>,
+  #[arg(long)]
+  pub end_line: Option<usize>,
+  /// Keep the subexpression info and lazy trace children references in the
+  /// messages alive, e.g. to pass them to later rpc calls or to expand trace
+  /// children with
+  #[arg(long)]
+  #[oai(default)]
+  #[serde(default)]
+  pub keep_references: bool,
+}
+
+#[derive(Args, Deserialize, Object, Serialize)]
+pub struct ExpandTraceChildrenCommand {
+  #[arg(long)]
+  pub session_id: Option<Ulid>,
+  pub lean_filepath: PathBuf,
+  /// The `lazy_children` reference of a trace node
+  pub children: Json,
+  #[arg(long)]
+  #[oai(default)]
+  #[serde(default)]
+  pub keep_references: bool,
+}
+
+#[derive(Args, Constructor, Deserialize, Object, Serialize)]
+pub struct WaitForFileCommand {
+  #[arg(long)]
+  pub session_id: Option<Ulid>,
+  pub lean_filepath: PathBuf,
+  /// Document version to wait for; defaults to the currently open version
+  #[arg(long)]
+  pub version: Option<usize>,
+  #[arg(long)]
+  pub timeout_ms: Option<u64>,
+  #[command(flatten)]
+  #[oai(default)]
+  #[serde(default)]
+  pub filter: DiagnosticFilter,
+}
+
+#[derive(Args, Deserialize, Object, Serialize)]
+pub struct LeanRpcCallCommand {
+  #[arg(long)]
+  pub session_id: Option<Ulid>,
+  #[command(flatten)]
+  pub location: Location,
+  pub method: String,
+  pub params: Option<Json>,
+  /// Keep the object references in the result alive instead of releasing them,
+  /// e.g. to pass them to later calls
+  #[arg(long)]
+  #[oai(default)]
+  #[serde(default)]
+  pub keep_references: bool,
+}
+
+#[derive(Args, Deserialize, Serialize)]
+pub struct GetInteractiveGoalsCommand {
+  #[command(flatten)]
+  pub location: Location,
+  /// Keep the subexpression info references in the goals alive, e.g. to pass
+  /// them to later rpc calls
+  #[arg(long)]
+  #[serde(default)]
+  pub keep_references: bool,
+}
+
+#[derive(Args, Deserialize, Object, Serialize)]
+pub struct LeanRpcReleaseCommand {
+  #[arg(long)]
+  pub session_id: Option<Ulid>,
+  pub lean_filepath: PathBuf,
+  pub refs: Vec<Json>,
+}
+
+#[derive(Args, Deserialize, Object, Serialize)]
+pub struct ReplyToServerRequestCommand {
+  #[arg(long)]
+  pub session_id: Option<Ulid>,
+  pub id: Json,
+  #[arg(long)]
+  pub result: Option<Json>,
+  #[arg(long)]
+  pub error: Option<Json>,
+}
+
+#[derive(Args, Deserialize, Object, Serialize)]
+pub struct RawMessageCommand {
+  #[arg(long)]
+  pub session_id: Option<Ulid>,
+  pub method: String,
+  pub params: Option<Json>,
+}
+
 pub enum SessionSetCommand {
   NewSession {
     sender: OneshotSender<Result<Session, AnyhowError>>,
@@ -0,0 +1,142 @@
+use anyhow::{Context, Error as AnyhowError};
+use mkutils::Utils;
+
+use crate::{
+  messages::text_document::INITIAL_TEXT_DOCUMENT_VERSION,
+  types::{Position, TextEdit},
+};
+
+pub struct Document {
+  pub text: String,
+  pub version: usize,
+}
+
+impl Document {
+  pub fn new(text: String) -> Self {
+    let version = INITIAL_TEXT_DOCUMENT_VERSION;
+
+    Self { text, version }
+  }
+
+  // NOTE: characters are counted in utf-16 code units and positions past the
+  // end of a line are clamped to the end of that line, per the lsp spec
+  fn offset(text: &str, position: &Position) -> Result<usize, AnyhowError> {
+    let mut line_offset = 0;
+
+    for _line in 0..position.line {
+      let newline_offset = text[line_offset..]
+        .find('\n')
+        .with_context(|| format!("line {} is past the end of the document", position.line))?;
+
+      line_offset += newline_offset + 1;
+    }
+
+    let line = text[line_offset..].split('\n').next().unwrap_or_default();
+    let line = line.strip_suffix('\r').unwrap_or(line);
+    let mut character = 0;
+
+    for (char_offset, chr) in line.char_indices() {
+      if position.character <= character {
+        return (line_offset + char_offset).ok();
+      }
+
+      character += chr.len_utf16();
+    }
+
+    (line_offset + line.len()).ok()
+  }
+
+  pub fn edited_text(&self, edits: &[TextEdit]) -> Result<String, AnyhowError> {
+    Self::edit_text(&self.text, edits)
+  }
+
+  // NOTE: edits are applied in order, each to the result of the previous one,
+  // matching the semantics of textDocument/didChange's contentChanges
+  pub fn edit_text(text: &str, edits: &[TextEdit]) -> Result<String, AnyhowError> {
+    let mut text = text.to_owned();
+
+    for edit in edits {
+      let start_offset = Self::offset(&text, &edit.range.start)?;
+      let end_offset = Self::offset(&text, &edit.range.end)?;
+
+      if end_offset < start_offset {
+        anyhow::bail!("edit range ends before it starts");
+      }
+
+      text.replace_range(start_offset..end_offset, &edit.text);
+    }
+
+    text.ok()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Document;
+  use crate::types::{Position, Range, TextEdit};
+
+  fn text_edit(start: (usize, usize), end: (usize, usize), text: &str) -> TextEdit {
+    let range = Range {
+      start: Position::new(start.0, start.1),
+      end: Position::new(end.0, end.1),
+    };
+
+    TextEdit {
+      range,
+      text: text.to_owned(),
+    }
+  }
+
+  fn edit_text(text: &str, edits: &[TextEdit]) -> String {
+    Document::edit_text(text, edits).expect("edits should apply")
+  }
+
+  #[test]
+  fn edits_apply_to_the_result_of_the_previous_edit() {
+    let edits = [
+      text_edit((0, 0), (0, 3), "def"),
+      text_edit((0, 3), (0, 3), "\n"),
+      text_edit((1, 1), (1, 2), "b"),
+      text_edit((2, 0), (2, 1), "Z"),
+    ];
+
+    assert_eq!(edit_text("abc x\ny", &edits), "def\n b\nZ");
+  }
+
+  #[test]
+  fn characters_are_counted_in_utf16_code_units() {
+    let text = "let 𝔸 := 𝔹";
+
+    assert_eq!(edit_text(text, &[text_edit((0, 4), (0, 6), "A")]), "let A := 𝔹");
+    assert_eq!(edit_text(text, &[text_edit((0, 6), (0, 6), "'")]), "let 𝔸' := 𝔹");
+    assert_eq!(edit_text(text, &[text_edit((0, 10), (0, 12), "B")]), "let 𝔸 := B");
+  }
+
+  #[test]
+  fn positions_inside_a_surrogate_pair_do_not_split_it() {
+    assert_eq!(edit_text("𝔸x", &[text_edit((0, 1), (0, 1), "!")]), "𝔸!x");
+  }
+
+  #[test]
+  fn positions_past_the_end_of_a_line_are_clamped() {
+    let text = "ab\ncd";
+
+    assert_eq!(edit_text(text, &[text_edit((0, 2), (0, 2), "X")]), "abX\ncd");
+    assert_eq!(edit_text(text, &[text_edit((0, 9), (0, 9), "X")]), "abX\ncd");
+    assert_eq!(edit_text("ab\r\ncd", &[text_edit((0, 9), (0, 9), "X")]), "abX\r\ncd");
+  }
+
+  #[test]
+  fn edits_can_end_at_the_end_of_the_document() {
+    assert_eq!(edit_text("ab\ncd", &[text_edit((1, 2), (1, 2), "!")]), "ab\ncd!");
+    assert_eq!(edit_text("ab\n", &[text_edit((1, 0), (1, 0), "cd")]), "ab\ncd");
+    assert_eq!(edit_text("ab\ncd", &[text_edit((0, 1), (1, 2), "")]), "a");
+  }
+
+  #[test]
+  fn out_of_range_edits_are_errors() {
+    assert!(Document::edit_text("ab\ncd", &[text_edit((2, 0), (2, 0), "x")]).is_err());
+    assert!(Document::edit_text("ab\ncd", &[text_edit((0, 0), (5, 0), "x")]).is_err());
+    assert!(Document::edit_text("ab\ncd", &[text_edit((1, 0), (0, 0), "x")]).is_err());
+  }
+}
@@ -0,0 +1,157 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Error as AnyhowError};
+use globset::{Glob, GlobMatcher};
+use mkutils::Utils;
+use notify::{
+  Event as NotifyEvent, EventKind, RecommendedWatcher, RecursiveMode, Result as NotifyResult, Watcher,
+  event::{ModifyKind, RenameMode},
+};
+use serde_json::Value as Json;
+use tokio::sync::mpsc::UnboundedReceiver as MpscUnboundedReceiver;
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum FileChangeType {
+  Created,
+  Changed,
+  Deleted,
+}
+
+impl FileChangeType {
+  pub fn new(event_kind: &EventKind) -> Option<Self> {
+    let file_change_type = match event_kind {
+      EventKind::Create(_create_kind) | EventKind::Modify(ModifyKind::Name(RenameMode::To)) => Self::Created,
+      EventKind::Remove(_remove_kind) | EventKind::Modify(ModifyKind::Name(RenameMode::From)) => Self::Deleted,
+      EventKind::Modify(_modify_kind) => Self::Changed,
+      EventKind::Any | EventKind::Access(_) | EventKind::Other => return None,
+    };
+
+    file_change_type.some()
+  }
+
+  // NOTE: [https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#fileChangeType]
+  pub fn lsp_type(self) -> usize {
+    match self {
+      Self::Created => 1,
+      Self::Changed => 2,
+      Self::Deleted => 3,
+    }
+  }
+
+  // NOTE: [https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#watchKind]
+  fn watch_kind(self) -> u64 {
+    match self {
+      Self::Created => 1,
+      Self::Changed => 2,
+      Self::Deleted => 4,
+    }
+  }
+}
+
+pub struct FileSystemWatcher {
+  base_dirpath: Option<PathBuf>,
+  glob_matcher: GlobMatcher,
+  watch_kind: u64,
+}
+
+impl FileSystemWatcher {
+  const DEFAULT_WATCH_KIND: u64 = 7;
+  const METHOD: &'static str = "workspace/didChangeWatchedFiles";
+
+  fn new(watcher: &Json) -> Result<Self, AnyhowError> {
+    let glob_pattern = watcher.get("globPattern").context("watcher has no glob pattern")?;
+    let (base_dirpath, pattern) = match glob_pattern {
+      Json::String(pattern) => (None, pattern.as_str()),
+      relative_pattern => {
+        // NOTE: the base uri is either a uri or a workspace folder
+        let base_uri = relative_pattern
+          .get("baseUri")
+          .context("relative pattern has no base uri")?;
+        let base_uri = base_uri
+          .as_str()
+          .or_else(|| base_uri.get("uri").and_then(Json::as_str))
+          .context("invalid base uri")?;
+        let pattern = relative_pattern
+          .get("pattern")
+          .and_then(Json::as_str)
+          .context("relative pattern has no pattern")?;
+
+        (crate::uri::filepath(base_uri)?.some(), pattern)
+      }
+    };
+    let glob_matcher = Glob::new(pattern)?.compile_matcher();
+    let watch_kind = watcher
+      .get("kind")
+      .and_then(Json::as_u64)
+      .unwrap_or(Self::DEFAULT_WATCH_KIND);
+    let file_system_watcher = Self {
+      base_dirpath,
+      glob_matcher,
+      watch_kind,
+    };
+
+    file_system_watcher.ok()
+  }
+
+  pub fn from_registrations<'a>(registrations: impl IntoIterator<Item = &'a Json>) -> Vec<Self> {
+    registrations
+      .into_iter()
+      .filter(|registration| registration.get("method").and_then(Json::as_str) == Self::METHOD.some())
+      .filter_map(|registration| registration.get("registerOptions")?.get("watchers")?.as_array())
+      .flatten()
+      .filter_map(|watcher| match Self::new(watcher) {
+        Ok(file_system_watcher) => file_system_watcher.some(),
+        Err(error) => {
+          tracing::warn!(%error, "ignoring invalid file system watcher");
+
+          None
+        }
+      })
+      .collect()
+  }
+
+  pub fn matches(&self, filepath: &Path, file_change_type: FileChangeType) -> bool {
+    if self.watch_kind & file_change_type.watch_kind() == 0 {
+      return false;
+    }
+
+    match &self.base_dirpath {
+      Some(base_dirpath) => filepath
+        .strip_prefix(base_dirpath)
+        .is_ok_and(|relative_filepath| self.glob_matcher.is_match(relative_filepath)),
+      None => self.glob_matcher.is_match(filepath),
+    }
+  }
+}
+
+pub struct FileWatcher {
+  _watcher: RecommendedWatcher,
+  events: MpscUnboundedReceiver<NotifyResult<NotifyEvent>>,
+}
+
+impl FileWatcher {
+  pub fn new(dirpath: &Path) -> Result<Self, AnyhowError> {
+    let (sender, events) = tokio::sync::mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event_res| sender.send(event_res).log_if_error().unit())?;
+
+    watcher.watch(dirpath, RecursiveMode::Recursive)?;
+
+    let file_watcher = Self {
+      _watcher: watcher,
+      events,
+    };
+
+    file_watcher.ok()
+  }
+
+  // NOTE: never resolves when there is no file watcher so that it can be
+  // selected on unconditionally
+  pub async fn next_event(file_watcher: &mut Option<Self>) -> Result<NotifyEvent, AnyhowError> {
+    let Some(file_watcher) = file_watcher else {
+      return std::future::pending().await;
+    };
+    let event = file_watcher.events.recv().await.context("file watcher closed")??;
+
+    event.ok()
+  }
+}
@@ -1,10 +1,13 @@
 use std::{
+  ffi::OsStr,
   io::Error as IoError,
   path::{Path, PathBuf},
+  process::ExitStatus,
 };
 
 use anyhow::{Context, Error as AnyhowError};
 use bytes::{Buf, BytesMut};
+use futures::StreamExt;
 use mkutils::{Event, EventReceiver, EventSender, IntoStream, Process, ToValue, Utils};
 use serde::{Serialize, de::DeserializeOwned};
 use tokio::{
@@ -15,7 +18,17 @@ use tokio::{
 };
 use tokio_stream::wrappers::{LinesStream, UnboundedReceiverStream as MpscUnboundedReceiverStream};
 
-use crate::{messages::Message, types::TaskStatus};
+use crate::{
+  messages::Message,
+  types::{EnvVar, TaskStatus},
+};
+
+#[derive(Clone)]
+pub struct LeanServerCommand {
+  pub program: String,
+  pub args: Vec<String>,
+  pub env: Vec<EnvVar>,
+}
 
 struct LeanServerStdout {
   buf: BytesMut,
@@ -31,6 +44,14 @@ impl LeanServerStdout {
     Self { buf, stdout }
   }
 
+  async fn read_buf(&mut self) -> Result<(), AnyhowError> {
+    if self.stdout.read_buf(&mut self.buf).await? == 0 {
+      anyhow::bail!("lean server stdout closed");
+    }
+
+    ().ok()
+  }
+
   // NOTE: [https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#headerPart]
   #[tracing::instrument(skip_all)]
   async fn next_message(&mut self) -> Result<BytesMut, AnyhowError> {
@@ -46,7 +67,7 @@ impl LeanServerStdout {
         break (separator_end_idx, content_length);
       }
 
-      self.stdout.read_buf(&mut self.buf).await?;
+      self.read_buf().await?;
     };
 
     // NOTE: skip ahead to the beginning of the response content
@@ -54,7 +75,7 @@ impl LeanServerStdout {
 
     // NOTE: read bytes until there are enough
     while self.buf.len() < content_length {
-      self.stdout.read_buf(&mut self.buf).await?;
+      self.read_buf().await?;
     }
 
     // NOTE: pop bytes from beginning of buffer
@@ -81,12 +102,14 @@ impl LeanServerProcess {
   pub fn new(
     project_dirpath: &Path,
     log_dirpath: Option<&Path>,
+    command: &LeanServerCommand,
     inputs: MpscUnboundedReceiver<Vec<u8>>,
     outputs: MpscUnboundedSender<BytesMut>,
     kill_event: EventReceiver,
   ) -> Result<Self, AnyhowError> {
     let inputs = inputs.into_stream();
-    let (child, stdin, stdout, stderr) = Self::process(&project_dirpath.absolute()?, log_dirpath)?.into_parts();
+    let (child, stdin, stdout, stderr) =
+      Self::process(&project_dirpath.absolute()?, log_dirpath, command)?.into_parts();
     let stdout = LeanServerStdout::new(stdout);
     let stderr = stderr.buf_reader_async().lines().into_stream();
     let lean_server = Self {
@@ -102,9 +125,20 @@ impl LeanServerProcess {
     lean_server.ok()
   }
 
-  fn process(project_dirpath: &Path, log_dirpath: Option<&Path>) -> Result<Process, AnyhowError> {
-    let env = log_dirpath.map(|log_dirpath| Self::LOG_DIRPATH_ENV_NAME.pair(log_dirpath));
-    let process = Process::new("lake", ["serve"], env, project_dirpath.some())?;
+  fn process(
+    project_dirpath: &Path,
+    log_dirpath: Option<&Path>,
+    command: &LeanServerCommand,
+  ) -> Result<Process, AnyhowError> {
+    let log_dirpath_env = log_dirpath.map(|log_dirpath| Self::LOG_DIRPATH_ENV_NAME.pair(log_dirpath.as_os_str()));
+    let command_env = command
+      .env
+      .iter()
+      .map(|env_var| env_var.name.as_str().pair(OsStr::new(&env_var.value)));
+    // NOTE: chain the command env last so that it can override the log dirpath
+    // env
+    let env = log_dirpath_env.into_iter().chain(command_env);
+    let process = Process::new(command.program.as_str(), &command.args, env, project_dirpath.some())?;
 
     process.ok()
   }
@@ -121,32 +155,57 @@ impl LeanServerProcess {
     ().ok()
   }
 
-  #[tracing::instrument(skip_all)]
-  pub async fn run(mut self) -> Result<(), AnyhowError> {
+  async fn process_io(&mut self) -> Result<(), AnyhowError> {
     loop {
       tokio::select! {
         input_byte_str_res = self.inputs.next_item_async() => self.write_to_process(&input_byte_str_res?).await?,
         output_byte_str_res = self.stdout.next_message() => self.outputs.send(output_byte_str_res?)?,
         message_res = self.stderr.next_item_async() => tracing::warn!(stderr_message = message_res??, "stderr message"),
-        exit_status_res = self.child.wait() => tracing::warn!(exit_status = %exit_status_res?, "lean server process ended"),
+        exit_status_res = self.child.wait() => return exit_status_res?.unit().ok(),
         () = self.kill_event.wait() => return self.child.kill().await?.ok(),
       }
     }
   }
+
+  // NOTE: stdout and stderr close around the same time as the child exits, so
+  // io errors are not treated as fatal until the exit status of the child has
+  // been collected
+  #[tracing::instrument(skip_all)]
+  pub async fn run(mut self) -> Result<ExitStatus, AnyhowError> {
+    if let Err(error) = self.process_io().await {
+      tracing::warn!(%error, "lean server process io failed");
+
+      if let Err(kill_error) = self.child.start_kill() {
+        tracing::warn!(%kill_error, "unable to kill lean server process");
+      }
+    }
+
+    let exit_status = self.child.wait().await?;
+
+    tracing::warn!(%exit_status, "lean server process ended");
+
+    exit_status.ok()
+  }
 }
 
 pub struct LeanServer {
   inputs: MpscUnboundedSender<Vec<u8>>,
   outputs: MpscUnboundedReceiverStream<BytesMut>,
   project_dirpath: PathBuf,
-  process_handle: JoinHandle<Result<(), AnyhowError>>,
+  process_handle: JoinHandle<Result<ExitStatus, AnyhowError>>,
   kill_event: EventSender,
 }
 
 impl LeanServer {
+  pub const DEFAULT_ARGS: [&'static str; 1] = ["serve"];
+  pub const DEFAULT_PROGRAM: &'static str = "lake";
   pub const LOG_DIRPATH_ENV_NAME: &'static str = LeanServerProcess::LOG_DIRPATH_ENV_NAME;
 
-  pub fn new(project_dirpath: &Path, log_dirpath: Option<&Path>) -> Result<Self, AnyhowError> {
+  pub fn new(
+    project_dirpath: &Path,
+    log_dirpath: Option<&Path>,
+    command: &LeanServerCommand,
+  ) -> Result<Self, AnyhowError> {
     // NOTE-97a211
     let project_dirpath = project_dirpath.absolute()?.into_owned();
     let (inputs, process_inputs) = tokio::sync::mpsc::unbounded_channel();
@@ -156,6 +215,7 @@ impl LeanServer {
     let process_handle = LeanServerProcess::new(
       &project_dirpath,
       log_dirpath,
+      command,
       process_inputs,
       process_outputs,
       process_kill_event,
@@ -192,13 +252,17 @@ impl LeanServer {
     ().ok()
   }
 
-  pub async fn recv<T: DeserializeOwned>(&mut self) -> Result<T, AnyhowError> {
-    self
-      .outputs
-      .next_item_async()
-      .await?
-      .to_value_from_json_byte_str::<T>()?
-      .ok()
+  // NOTE: returns `None` once the process has ended and all of its messages
+  // have been received
+  pub async fn recv<T: DeserializeOwned>(&mut self) -> Result<Option<T>, AnyhowError> {
+    let Some(json_byte_str) = self.outputs.next().await else { return None.ok() };
+
+    json_byte_str.to_value_from_json_byte_str::<T>()?.some().ok()
+  }
+
+  // NOTE: must only be awaited once, after `Self::recv()` has returned `None`
+  pub async fn wait(&mut self) -> Result<ExitStatus, AnyhowError> {
+    (&mut self.process_handle).await?
   }
 
   pub fn process_status(&self) -> TaskStatus {
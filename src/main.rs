@@ -3,16 +3,22 @@
 mod cli_args;
 mod client;
 mod commands;
+mod document;
+mod file_watcher;
 mod lean_server;
 mod macros;
 mod messages;
+mod request_error;
 mod server;
 mod session;
 mod session_runner;
 mod session_set;
 mod session_set_runner;
 mod stream;
+mod tagged_text;
 mod types;
+mod uri;
+mod workspace_edit;
 
 use anyhow::Error as AnyhowError;
 use clap::Parser;
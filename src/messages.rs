@@ -1,6 +1,10 @@
+pub mod general;
 pub mod initialize;
+pub mod lean_lsp;
 pub mod lean_rpc;
 pub mod text_document;
+pub mod window;
+pub mod workspace;
 
 use std::path::Path;
 
@@ -9,6 +13,8 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value as Json;
 use ulid::Ulid;
 
+use crate::types::{Diagnostic, Range, TextEdit};
+
 #[derive(Clone, Deserialize, Display, Eq, From, Hash, PartialEq, Serialize)]
 #[serde(untagged)]
 pub enum Id {
@@ -19,6 +25,7 @@ pub enum Id {
 
 pub struct Message {
   pub id: Id,
+  pub method: String,
   pub json: Json,
 }
 
@@ -31,8 +38,9 @@ impl Message {
       "method": method,
       "params": params,
     });
+    let method = method.to_owned();
 
-    Self { id, json }
+    Self { id, method, json }
   }
 
   fn notification(method: &str, params: &Json) -> Json {
@@ -43,6 +51,55 @@ impl Message {
     })
   }
 
+  // NOTE: params are omitted rather than sent as null when not given, as null
+  // is not a valid value for them
+  fn without_null_params(mut json: Json, params: Option<&Json>) -> Json {
+    if params.is_none()
+      && let Some(json_object) = json.as_object_mut()
+    {
+      json_object.remove("params");
+    }
+
+    json
+  }
+
+  pub fn raw_request(method: &str, params: Option<&Json>) -> Self {
+    let mut message = Self::request(method, params.unwrap_or(&Json::Null));
+
+    message.json = Self::without_null_params(message.json, params);
+
+    message
+  }
+
+  pub fn raw_notification(method: &str, params: Option<&Json>) -> Json {
+    let json = Self::notification(method, params.unwrap_or(&Json::Null));
+
+    Self::without_null_params(json, params)
+  }
+
+  pub fn response(id: &Id, result: &Json) -> Json {
+    serde_json::json!({
+      "jsonrpc": "2.0",
+      "id": id,
+      "result": result,
+    })
+  }
+
+  pub fn response_error(code: i64, message: &str) -> Json {
+    serde_json::json!({
+      "code": code,
+      "message": message,
+    })
+  }
+
+  pub fn error_response(id: &Id, error: &Json) -> Json {
+    serde_json::json!({
+      "jsonrpc": "2.0",
+      "id": id,
+      "error": error,
+    })
+  }
+
   pub fn initialize_request(root_path: &Path, root_uri: &str, name: &str) -> Self {
     let params = crate::messages::initialize::initialize_params(root_path, root_uri, name, std::process::id());
 
@@ -56,9 +113,15 @@ impl Message {
     Self::notification("initialized", &params)
   }
 
+  pub fn cancel_request_notification(id: &Id) -> Json {
+    let params = crate::messages::general::cancel_request_params(id);
+
+    Self::notification("$/cancelRequest", &params)
+  }
+
   #[allow(clippy::unused_self)]
-  pub fn text_document_did_open_notification(text: &str, uri: &str) -> Json {
-    let params = crate::messages::text_document::did_open_notification_params(text, uri);
+  pub fn text_document_did_open_notification(text: &str, uri: &str, version: usize) -> Json {
+    let params = crate::messages::text_document::did_open_notification_params(text, uri, version);
 
     Self::notification("textDocument/didOpen", &params)
   }
@@ -70,6 +133,18 @@ impl Message {
     Self::notification("textDocument/didChange", &params)
   }
 
+  pub fn text_document_did_change_edits_notification(edits: &[TextEdit], uri: &str, version: usize) -> Json {
+    let params = crate::messages::text_document::did_change_edits_notification_params(edits, uri, version);
+
+    Self::notification("textDocument/didChange", &params)
+  }
+
+  pub fn text_document_did_save_notification(text: &str, uri: &str) -> Json {
+    let params = crate::messages::text_document::did_save_notification_params(text, uri);
+
+    Self::notification("textDocument/didSave", &params)
+  }
+
   #[allow(clippy::unused_self)]
   pub fn text_document_did_close_notification(uri: &str) -> Json {
     let params = crate::messages::text_document::did_close_notification_params(uri);
@@ -84,18 +159,117 @@ impl Message {
     Self::request("textDocument/hover", &params)
   }
 
+  pub fn text_document_definition_request(uri: &str, line: usize, character: usize) -> Self {
+    let params = crate::messages::text_document::position_params(uri, line, character);
+
+    Self::request("textDocument/definition", &params)
+  }
+
+  pub fn text_document_declaration_request(uri: &str, line: usize, character: usize) -> Self {
+    let params = crate::messages::text_document::position_params(uri, line, character);
+
+    Self::request("textDocument/declaration", &params)
+  }
+
+  pub fn text_document_type_definition_request(uri: &str, line: usize, character: usize) -> Self {
+    let params = crate::messages::text_document::position_params(uri, line, character);
+
+    Self::request("textDocument/typeDefinition", &params)
+  }
+
+  pub fn text_document_references_request(uri: &str, line: usize, character: usize, include_declaration: bool) -> Self {
+    let params = crate::messages::text_document::references_params(uri, line, character, include_declaration);
+
+    Self::request("textDocument/references", &params)
+  }
+
+  pub fn text_document_prepare_rename_request(uri: &str, line: usize, character: usize) -> Self {
+    let params = crate::messages::text_document::position_params(uri, line, character);
+
+    Self::request("textDocument/prepareRename", &params)
+  }
+
+  pub fn text_document_rename_request(uri: &str, line: usize, character: usize, new_name: &str) -> Self {
+    let params = crate::messages::text_document::rename_params(uri, line, character, new_name);
+
+    Self::request("textDocument/rename", &params)
+  }
+
+  pub fn text_document_completion_request(
+    uri: &str,
+    line: usize,
+    character: usize,
+    trigger_kind: usize,
+    trigger_character: Option<&str>,
+  ) -> Self {
+    let params =
+      crate::messages::text_document::completion_params(uri, line, character, trigger_kind, trigger_character);
+
+    Self::request("textDocument/completion", &params)
+  }
+
+  pub fn completion_item_resolve_request(item: &Json) -> Self {
+    Self::request("completionItem/resolve", item)
+  }
+
   pub fn text_document_document_symbol_request(uri: &str) -> Self {
     let params = crate::messages::text_document::document_symbol_params(uri);
 
     Self::request("textDocument/documentSymbol", &params)
   }
 
-  pub fn text_document_document_code_action_request(uri: &str) -> Self {
-    let params = crate::messages::text_document::document_code_action_params(uri);
+  pub fn text_document_document_code_action_request(uri: &str, range: &Range, diagnostics: &[Diagnostic]) -> Self {
+    let params = crate::messages::text_document::document_code_action_params(uri, range, diagnostics);
 
     Self::request("textDocument/codeAction", &params)
   }
 
+  pub fn text_document_prepare_call_hierarchy_request(uri: &str, line: usize, character: usize) -> Self {
+    let params = crate::messages::text_document::position_params(uri, line, character);
+
+    Self::request("textDocument/prepareCallHierarchy", &params)
+  }
+
+  pub fn call_hierarchy_incoming_calls_request(item: &Json) -> Self {
+    let params = crate::messages::text_document::call_hierarchy_calls_params(item);
+
+    Self::request("callHierarchy/incomingCalls", &params)
+  }
+
+  pub fn call_hierarchy_outgoing_calls_request(item: &Json) -> Self {
+    let params = crate::messages::text_document::call_hierarchy_calls_params(item);
+
+    Self::request("callHierarchy/outgoingCalls", &params)
+  }
+
+  pub fn text_document_inlay_hint_request(uri: &str, range: &Range) -> Self {
+    let params = crate::messages::text_document::inlay_hint_params(uri, range);
+
+    Self::request("textDocument/inlayHint", &params)
+  }
+
+  pub fn text_document_document_highlight_request(uri: &str, line: usize, character: usize) -> Self {
+    let params = crate::messages::text_document::position_params(uri, line, character);
+
+    Self::request("textDocument/documentHighlight", &params)
+  }
+
+  pub fn text_document_semantic_tokens_full_request(uri: &str) -> Self {
+    let params = crate::messages::text_document::semantic_tokens_params(uri);
+
+    Self::request("textDocument/semanticTokens/full", &params)
+  }
+
+  pub fn text_document_semantic_tokens_range_request(uri: &str, range: &Range) -> Self {
+    let params = crate::messages::text_document::semantic_tokens_range_params(uri, range);
+
+    Self::request("textDocument/semanticTokens/range", &params)
+  }
+
+  pub fn code_action_resolve_request(action: &Json) -> Self {
+    Self::request("codeAction/resolve", action)
+  }
+
   pub fn text_document_folding_range_request(uri: &str) -> Self {
     let params = crate::messages::text_document::folding_range_params(uri);
 
@@ -108,9 +282,58 @@ impl Message {
     Self::request("$/lean/rpc/connect", &params)
   }
 
+  pub fn lean_rpc_call_request(
+    uri: &str,
+    line: usize,
+    character: usize,
+    session_id: &Json,
+    method: &str,
+    params: &Json,
+  ) -> Self {
+    let params = crate::messages::lean_rpc::call_params(uri, line, character, session_id, method, params);
+
+    Self::request("$/lean/rpc/call", &params)
+  }
+
+  pub fn lean_rpc_keep_alive_notification(uri: &str, session_id: &Json) -> Json {
+    let params = crate::messages::lean_rpc::keep_alive_params(uri, session_id);
+
+    Self::notification("$/lean/rpc/keepAlive", &params)
+  }
+
+  pub fn lean_rpc_release_notification(uri: &str, session_id: &Json, refs: &[Json]) -> Json {
+    let params = crate::messages::lean_rpc::release_params(uri, session_id, refs);
+
+    Self::notification("$/lean/rpc/release", &params)
+  }
+
   pub fn lean_rpc_get_plain_goals_request(uri: &str, line: usize, character: usize) -> Self {
     let params = crate::messages::lean_rpc::get_plain_goals_params(uri, line, character);
 
     Self::request("$/lean/plainGoal", &params)
   }
+
+  pub fn lean_rpc_get_plain_term_goal_request(uri: &str, line: usize, character: usize) -> Self {
+    let params = crate::messages::lean_rpc::get_plain_goals_params(uri, line, character);
+
+    Self::request("$/lean/plainTermGoal", &params)
+  }
+
+  pub fn workspace_symbol_request(query: &str) -> Self {
+    let params = crate::messages::workspace::symbol_params(query);
+
+    Self::request("workspace/symbol", &params)
+  }
+
+  pub fn workspace_did_change_watched_files_notification(changes: &[Json]) -> Json {
+    let params = crate::messages::workspace::did_change_watched_files_notification_params(changes);
+
+    Self::notification("workspace/didChangeWatchedFiles", &params)
+  }
+
+  pub fn lean_lsp_server_lifecycle_notification(event: &str, restart_count: usize, exit_status: Option<&str>) -> Json {
+    let params = crate::messages::lean_lsp::server_lifecycle_params(event, restart_count, exit_status);
+
+    Self::notification("$/lean-lsp/serverLifecycle", &params)
+  }
 }
@@ -0,0 +1,7 @@
+use serde_json::Value as Json;
+
+use crate::messages::Id;
+
+pub fn cancel_request_params(id: &Id) -> Json {
+  serde_json::json!({"id": id})
+}
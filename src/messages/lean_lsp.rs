@@ -0,0 +1,9 @@
+use serde_json::Value as Json;
+
+pub fn server_lifecycle_params(event: &str, restart_count: usize, exit_status: Option<&str>) -> Json {
+  serde_json::json!({
+    "event": event,
+    "restartCount": restart_count,
+    "exitStatus": exit_status,
+  })
+}
@@ -4,6 +4,43 @@ pub fn connect_params(uri: &str) -> Json {
   serde_json::json!({"uri": uri})
 }
 
+pub fn call_params(uri: &str, line: usize, character: usize, session_id: &Json, method: &str, params: &Json) -> Json {
+  serde_json::json!({
+    "textDocument": {
+      "uri": uri,
+    },
+    "position": {
+      "line": line,
+      "character": character,
+    },
+    "sessionId": session_id,
+    "method": method,
+    "params": params,
+  })
+}
+
+pub fn keep_alive_params(uri: &str, session_id: &Json) -> Json {
+  serde_json::json!({"uri": uri, "sessionId": session_id})
+}
+
+pub fn release_params(uri: &str, session_id: &Json, refs: &[Json]) -> Json {
+  serde_json::json!({"uri": uri, "sessionId": session_id, "refs": refs})
+}
+
+// NOTE: lean's line ranges exclude their end line
+pub fn get_interactive_diagnostics_params(start_line: Option<usize>, end_line: Option<usize>) -> Json {
+  if start_line.is_none() && end_line.is_none() {
+    return serde_json::json!({});
+  }
+
+  serde_json::json!({
+    "lineRange": {
+      "start": start_line.unwrap_or_default(),
+      "end": end_line.map_or(usize::MAX, |end_line| end_line + 1),
+    },
+  })
+}
+
 pub fn get_plain_goals_params(uri: &str, line: usize, character: usize) -> Json {
   serde_json::json!({
     "textDocument": {
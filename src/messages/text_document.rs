@@ -1,17 +1,19 @@
 use serde_json::Value as Json;
 
+use crate::types::{Diagnostic, Range, TextEdit};
+
 pub const INITIAL_TEXT_DOCUMENT_VERSION: usize = 0;
 
 const LEAN_LANGUAGE_ID: &str = "lean4";
 
-pub fn did_open_notification_params(text: &str, uri: &str) -> Json {
+pub fn did_open_notification_params(text: &str, uri: &str, version: usize) -> Json {
   serde_json::json!({
     "dependencyBuildMode": "never",
     "textDocument": {
       "languageId": LEAN_LANGUAGE_ID,
       "text": text,
       "uri": uri,
-      "version": INITIAL_TEXT_DOCUMENT_VERSION,
+      "version": version,
     },
   })
 }
@@ -28,6 +30,25 @@ pub fn did_change_notification_params(text: &str, uri: &str, version: usize) ->
   })
 }
 
+pub fn did_change_edits_notification_params(edits: &[TextEdit], uri: &str, version: usize) -> Json {
+  serde_json::json!({
+    "textDocument": {
+      "uri": uri,
+      "version": version,
+    },
+    "contentChanges": edits,
+  })
+}
+
+pub fn did_save_notification_params(text: &str, uri: &str) -> Json {
+  serde_json::json!({
+    "textDocument": {
+      "uri": uri,
+    },
+    "text": text,
+  })
+}
+
 pub fn did_close_notification_params(uri: &str) -> Json {
   serde_json::json!({
     "textDocument": {
@@ -48,17 +69,78 @@ pub fn hover_params(uri: &str, line: usize, character: usize) -> Json {
   })
 }
 
+pub fn position_params(uri: &str, line: usize, character: usize) -> Json {
+  serde_json::json!({
+    "textDocument": {
+      "uri": uri,
+    },
+    "position": {
+      "line": line,
+      "character": character,
+    },
+  })
+}
+
+pub fn references_params(uri: &str, line: usize, character: usize, include_declaration: bool) -> Json {
+  serde_json::json!({
+    "textDocument": {
+      "uri": uri,
+    },
+    "position": {
+      "line": line,
+      "character": character,
+    },
+    "context": {
+      "includeDeclaration": include_declaration,
+    },
+  })
+}
+
+pub fn rename_params(uri: &str, line: usize, character: usize, new_name: &str) -> Json {
+  serde_json::json!({
+    "textDocument": {
+      "uri": uri,
+    },
+    "position": {
+      "line": line,
+      "character": character,
+    },
+    "newName": new_name,
+  })
+}
+
+pub fn completion_params(
+  uri: &str,
+  line: usize,
+  character: usize,
+  trigger_kind: usize,
+  trigger_character: Option<&str>,
+) -> Json {
+  serde_json::json!({
+    "textDocument": {
+      "uri": uri,
+    },
+    "position": {
+      "line": line,
+      "character": character,
+    },
+    "context": {
+      "triggerKind": trigger_kind,
+      "triggerCharacter": trigger_character,
+    },
+  })
+}
+
 pub fn document_symbol_params(uri: &str) -> Json {
   serde_json::json!({"textDocument": {"uri": uri}})
 }
 
-pub fn document_code_action_params(uri: &str) -> Json {
+// NOTE: code actions are requested explicitly, i.e. with the invoked trigger
+// kind
+pub fn document_code_action_params(uri: &str, range: &Range, diagnostics: &[Diagnostic]) -> Json {
   serde_json::json!({
-    "context": {"diagnostics": [], "triggerKind": 2},
-    "range": {
-      "end": {"character": 0, "line": 0},
-      "start": {"character": 0, "line": 0},
-    },
+    "context": {"diagnostics": diagnostics, "triggerKind": 1},
+    "range": range,
     "textDocument": {"uri": uri},
   })
 }
@@ -66,3 +148,19 @@ pub fn document_code_action_params(uri: &str) -> Json {
 pub fn folding_range_params(uri: &str) -> Json {
   serde_json::json!({"textDocument": {"uri": uri}})
 }
+
+pub fn call_hierarchy_calls_params(item: &Json) -> Json {
+  serde_json::json!({"item": item})
+}
+
+pub fn inlay_hint_params(uri: &str, range: &Range) -> Json {
+  serde_json::json!({"range": range, "textDocument": {"uri": uri}})
+}
+
+pub fn semantic_tokens_params(uri: &str) -> Json {
+  serde_json::json!({"textDocument": {"uri": uri}})
+}
+
+pub fn semantic_tokens_range_params(uri: &str, range: &Range) -> Json {
+  serde_json::json!({"range": range, "textDocument": {"uri": uri}})
+}
@@ -0,0 +1,5 @@
+use serde_json::Value as Json;
+
+pub fn show_document_result(success: bool) -> Json {
+  serde_json::json!({"success": success})
+}
@@ -0,0 +1,33 @@
+use serde_json::Value as Json;
+
+pub fn configuration_result(params: &Json) -> Json {
+  let item_count = params.get("items").and_then(Json::as_array).map_or(0, Vec::len);
+
+  // NOTE: [https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#workspace_configuration]
+  // null means that the client has no configuration for the item
+  std::iter::repeat_n(Json::Null, item_count).collect()
+}
+
+pub fn symbol_params(query: &str) -> Json {
+  serde_json::json!({"query": query})
+}
+
+pub fn file_event(uri: &str, change_type: usize) -> Json {
+  serde_json::json!({
+    "uri": uri,
+    "type": change_type,
+  })
+}
+
+pub fn did_change_watched_files_notification_params(changes: &[Json]) -> Json {
+  serde_json::json!({
+    "changes": changes,
+  })
+}
+
+pub fn apply_edit_result(applied: bool, failure_reason: Option<&str>) -> Json {
+  serde_json::json!({
+    "applied": applied,
+    "failureReason": failure_reason,
+  })
+}
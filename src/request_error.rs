@@ -0,0 +1,47 @@
+use std::time::Duration;
+
+use derive_more::Display;
+use poem::http::StatusCode;
+
+#[derive(Clone, Debug, Display)]
+pub enum RequestFailure {
+  #[display("lean server process ended with {exit_status}")]
+  LeanServerExited { exit_status: String },
+
+  #[display("lean server is unavailable: {reason}")]
+  LeanServerUnavailable { reason: String },
+
+  #[display("lean server responded with error {code}: {message}")]
+  ResponseError { code: i64, message: String },
+
+  #[display("session was killed")]
+  SessionKilled,
+
+  #[display("session ended")]
+  SessionEnded,
+
+  #[display("timed out after {timeout:?}")]
+  TimedOut { timeout: Duration },
+}
+
+#[derive(Debug, Display)]
+#[display("{request} request failed: {failure}")]
+pub struct RequestError {
+  pub request: String,
+  pub failure: RequestFailure,
+}
+
+impl RequestError {
+  pub fn status_code(&self) -> StatusCode {
+    match self.failure {
+      RequestFailure::ResponseError { .. } => StatusCode::BAD_GATEWAY,
+      RequestFailure::TimedOut { .. } => StatusCode::GATEWAY_TIMEOUT,
+      RequestFailure::LeanServerExited { .. }
+      | RequestFailure::LeanServerUnavailable { .. }
+      | RequestFailure::SessionKilled
+      | RequestFailure::SessionEnded => StatusCode::SERVICE_UNAVAILABLE,
+    }
+  }
+}
+
+impl std::error::Error for RequestError {}
@@ -21,10 +21,27 @@ use tokio::task::JoinHandle;
 use ulid::Ulid;
 
 use crate::{
-  commands::{CloseFileCommand, HoverFileCommand, NewSessionCommand, OpenFileCommand},
+  commands::{
+    ApplyCodeActionCommand, ApplyWorkspaceEditCommand, CloseFileCommand, ExpandTraceChildrenCommand,
+    GetCallHierarchyCommand, GetCodeActionsCommand, GetCompletionsCommand, GetDiagnosticsCommand,
+    GetDocumentHighlightsCommand, GetDocumentSymbolsCommand, GetFileCommand, GetFoldingRangesCommand,
+    GetInlayHintsCommand, GetInteractiveDiagnosticsCommand, GetReferencesCommand, GetSemanticTokensCommand,
+    GetSemanticTokensRangeCommand, GetWorkspaceSymbolsCommand, GoToCommand, HoverFileCommand, LeanRpcCallCommand,
+    LeanRpcReleaseCommand, NewSessionCommand, OpenFileCommand, PrepareRenameCommand, RawMessageCommand, RenameCommand,
+    ReplyToServerRequestCommand, ResolveCompletionItemCommand, SaveFileCommand, WaitForFileCommand,
+  },
+  request_error::RequestError,
   server::{
-    requests::ChangeFileRequest,
-    responses::{GetPlainGoalsResponse, GetSessionsResponse, HoverFileResponse, NewSessionResponse},
+    requests::{ChangeFileRequest, EditFileRequest},
+    responses::{
+      ApplyWorkspaceEditResponse, ExpandTraceChildrenResponse, GetCallHierarchyResponse, GetCodeActionsResponse,
+      GetCompletionsResponse, GetDiagnosticsResponse, GetDocumentHighlightsResponse, GetDocumentSymbolsResponse,
+      GetFileResponse, GetFoldingRangesResponse, GetInlayHintsResponse, GetInteractiveDiagnosticsResponse,
+      GetInteractiveGoalsResponse, GetLocationsResponse, GetPlainGoalsResponse, GetPlainTermGoalResponse,
+      GetSemanticTokensResponse, GetSessionsResponse, GetWorkspaceSymbolsResponse, HoverFileResponse,
+      LeanRpcCallResponse, NewSessionResponse, OpenFileResponse, PrepareRenameResponse, RawRequestResponse,
+      RenameResponse, ResolveCompletionItemResponse,
+    },
   },
   session::Session,
   session_set::SessionSet,
@@ -42,18 +59,52 @@ pub struct Server {
 impl Server {
   pub const DEFAULT_PORT: u16 = 8080;
   pub const IPV4_ADDR: Ipv4Addr = Ipv4Addr::UNSPECIFIED;
+  pub const PATH_APPLY_CODE_ACTION: &'static str = "/session/code-action/apply";
+  pub const PATH_APPLY_WORKSPACE_EDIT: &'static str = "/session/workspace-edit/apply";
+  pub const PATH_COMPLETION_RESOLVE: &'static str = "/session/completion/resolve";
+  pub const PATH_FILE_CALL_HIERARCHY: &'static str = "/session/file/call-hierarchy";
   pub const PATH_FILE_CHANGE: &'static str = "/session/file/change";
   pub const PATH_FILE_CLOSE: &'static str = "/session/file/close";
+  pub const PATH_FILE_CODE_ACTIONS: &'static str = "/session/file/code-actions";
+  pub const PATH_FILE_COMPLETION: &'static str = "/session/file/completion";
+  pub const PATH_FILE_DECLARATION: &'static str = "/session/file/declaration";
+  pub const PATH_FILE_DEFINITION: &'static str = "/session/file/definition";
+  pub const PATH_FILE_DIAGNOSTICS: &'static str = "/session/file/diagnostics";
+  pub const PATH_FILE_DOCUMENT_HIGHLIGHTS: &'static str = "/session/file/document-highlights";
+  pub const PATH_FILE_DOCUMENT_SYMBOLS: &'static str = "/session/file/document-symbols";
+  pub const PATH_FILE_EDIT: &'static str = "/session/file/edit";
+  pub const PATH_FILE_FOLDING_RANGES: &'static str = "/session/file/folding-ranges";
+  pub const PATH_FILE_GET: &'static str = "/session/file/get";
   pub const PATH_FILE_HOVER: &'static str = "/session/file/hover";
+  pub const PATH_FILE_INLAY_HINTS: &'static str = "/session/file/inlay-hints";
+  pub const PATH_FILE_INTERACTIVE_DIAGNOSTICS: &'static str = "/session/file/interactive-diagnostics";
   pub const PATH_FILE_OPEN: &'static str = "/session/file/open";
+  pub const PATH_FILE_PREPARE_RENAME: &'static str = "/session/file/prepare-rename";
+  pub const PATH_FILE_REFERENCES: &'static str = "/session/file/references";
+  pub const PATH_FILE_RENAME: &'static str = "/session/file/rename";
+  pub const PATH_FILE_SAVE: &'static str = "/session/file/save";
+  pub const PATH_FILE_SEMANTIC_TOKENS: &'static str = "/session/file/semantic-tokens";
+  pub const PATH_FILE_SEMANTIC_TOKENS_RANGE: &'static str = "/session/file/semantic-tokens/range";
+  pub const PATH_FILE_TRACE_CHILDREN: &'static str = "/session/file/interactive-diagnostics/trace-children";
+  pub const PATH_FILE_TYPE_DEFINITION: &'static str = "/session/file/type-definition";
+  pub const PATH_FILE_WAIT: &'static str = "/session/file/wait";
+  pub const PATH_GET_INTERACTIVE_GOALS: &'static str = "/session/info-view/interactive-goals";
   pub const PATH_GET_NOTIFICATIONS: &'static str = "/session/notifications";
   pub const PATH_GET_PLAIN_GOALS: &'static str = "/session/info-view/plain-goals";
+  pub const PATH_GET_PLAIN_TERM_GOAL: &'static str = "/session/info-view/plain-term-goal";
   pub const PATH_GET_SESSIONS: &'static str = "/session";
   pub const PATH_GET_SESSION_SET_STATUS: &'static str = "/session-set/status";
   pub const PATH_KILL: &'static str = "/";
+  pub const PATH_LEAN_RPC_CALL: &'static str = "/session/lean-rpc/call";
+  pub const PATH_LEAN_RPC_RELEASE: &'static str = "/session/lean-rpc/release";
+  pub const PATH_LSP_NOTIFY: &'static str = "/session/lsp/notify";
+  pub const PATH_LSP_REQUEST: &'static str = "/session/lsp/request";
   pub const PATH_NEW_SESSION: &'static str = "/session/new";
+  pub const PATH_REPLY_TO_SERVER_REQUEST: &'static str = "/session/server-request/reply";
+  pub const PATH_WORKSPACE_SYMBOLS: &'static str = "/session/workspace/symbols";
   pub const QUERY_PARAM_CHARACTER: &'static str = "character";
   pub const QUERY_PARAM_FILEPATH: &'static str = "filepath";
+  pub const QUERY_PARAM_KEEP_REFERENCES: &'static str = "keep_references";
   pub const QUERY_PARAM_LINE: &'static str = "line";
   pub const QUERY_PARAM_METHODS: &'static str = "methods";
   pub const QUERY_PARAM_SESSION_ID: &'static str = "session_id";
@@ -63,6 +114,15 @@ impl Server {
   const TITLE: &'static str = std::env!("CARGO_PKG_NAME");
   const VERSION: &'static str = std::env!("CARGO_PKG_VERSION");
 
+  // NOTE: surfaces typed request failures with a more specific status code than
+  // the default internal server error
+  fn request_error(error: AnyhowError) -> PoemError {
+    match error.downcast_ref::<RequestError>() {
+      Some(request_error) => PoemError::from_string(request_error.to_string(), request_error.status_code()),
+      None => error.into(),
+    }
+  }
+
   fn new() -> (Self, EventReceiver) {
     let (session_set, join_handle) = SessionSet::new();
     let (kill_event_sender, kill_event_receiver) = Event::new();
@@ -118,23 +178,23 @@ impl Server {
     &self,
     PoemJson(command): PoemJson<NewSessionCommand>,
   ) -> Result<PoemJson<NewSessionResponse>, PoemError> {
-    let session = self
-      .session_set
-      .new_session(command.lean_path, command.lean_server_log_dirpath)
-      .await?;
+    let session = self.session_set.new_session(command).await?;
 
-    session.initialize().await?;
+    session.initialize().await.map_err(Self::request_error)?;
 
     session.id().convert::<NewSessionResponse>().poem_json().ok()
   }
 
   #[oai(path = "/session/file/open", method = "post")]
-  async fn open_file(&self, PoemJson(command): PoemJson<OpenFileCommand>) -> Result<PoemJson<()>, PoemError> {
+  async fn open_file(
+    &self,
+    PoemJson(command): PoemJson<OpenFileCommand>,
+  ) -> Result<PoemJson<OpenFileResponse>, PoemError> {
     self
       .session_set
       .get_session(command.session_id)
       .await?
-      .open_file(command.lean_filepath)
+      .open_file(command.lean_filepath, command.text)
       .await?
       .poem_json()
       .ok()
@@ -152,6 +212,45 @@ impl Server {
       .ok()
   }
 
+  #[oai(path = "/session/file/edit", method = "post")]
+  async fn edit_file(&self, PoemJson(command): PoemJson<EditFileRequest>) -> Result<PoemJson<()>, PoemError> {
+    self
+      .session_set
+      .get_session(command.session_id)
+      .await?
+      .edit_file(command.lean_filepath, command.edits)
+      .await?
+      .poem_json()
+      .ok()
+  }
+
+  #[oai(path = "/session/file/get", method = "post")]
+  async fn get_file(
+    &self,
+    PoemJson(command): PoemJson<GetFileCommand>,
+  ) -> Result<PoemJson<GetFileResponse>, PoemError> {
+    self
+      .session_set
+      .get_session(command.session_id)
+      .await?
+      .get_file(command.lean_filepath)
+      .await?
+      .poem_json()
+      .ok()
+  }
+
+  #[oai(path = "/session/file/save", method = "post")]
+  async fn save_file(&self, PoemJson(command): PoemJson<SaveFileCommand>) -> Result<PoemJson<()>, PoemError> {
+    self
+      .session_set
+      .get_session(command.session_id)
+      .await?
+      .save_file(command.lean_filepath)
+      .await?
+      .poem_json()
+      .ok()
+  }
+
   #[oai(path = "/session/file/close", method = "post")]
   async fn close_file(&self, PoemJson(command): PoemJson<CloseFileCommand>) -> Result<PoemJson<()>, PoemError> {
     self
@@ -174,7 +273,381 @@ impl Server {
       .get_session(command.session_id)
       .await?
       .hover_file(command.location)
+      .await
+      .map_err(Self::request_error)?
+      .poem_json()
+      .ok()
+  }
+
+  #[oai(path = "/session/file/definition", method = "post")]
+  async fn get_definition(
+    &self,
+    PoemJson(command): PoemJson<GoToCommand>,
+  ) -> Result<PoemJson<GetLocationsResponse>, PoemError> {
+    self
+      .session_set
+      .get_session(command.session_id)
+      .await?
+      .get_definition(command.location)
+      .await
+      .map_err(Self::request_error)?
+      .poem_json()
+      .ok()
+  }
+
+  #[oai(path = "/session/file/declaration", method = "post")]
+  async fn get_declaration(
+    &self,
+    PoemJson(command): PoemJson<GoToCommand>,
+  ) -> Result<PoemJson<GetLocationsResponse>, PoemError> {
+    self
+      .session_set
+      .get_session(command.session_id)
+      .await?
+      .get_declaration(command.location)
+      .await
+      .map_err(Self::request_error)?
+      .poem_json()
+      .ok()
+  }
+
+  #[oai(path = "/session/file/type-definition", method = "post")]
+  async fn get_type_definition(
+    &self,
+    PoemJson(command): PoemJson<GoToCommand>,
+  ) -> Result<PoemJson<GetLocationsResponse>, PoemError> {
+    self
+      .session_set
+      .get_session(command.session_id)
+      .await?
+      .get_type_definition(command.location)
+      .await
+      .map_err(Self::request_error)?
+      .poem_json()
+      .ok()
+  }
+
+  #[oai(path = "/session/file/references", method = "post")]
+  async fn get_references(
+    &self,
+    PoemJson(command): PoemJson<GetReferencesCommand>,
+  ) -> Result<PoemJson<GetLocationsResponse>, PoemError> {
+    self
+      .session_set
+      .get_session(command.session_id)
+      .await?
+      .get_references(command.location, command.include_declaration)
+      .await
+      .map_err(Self::request_error)?
+      .poem_json()
+      .ok()
+  }
+
+  #[oai(path = "/session/file/prepare-rename", method = "post")]
+  async fn prepare_rename(
+    &self,
+    PoemJson(command): PoemJson<PrepareRenameCommand>,
+  ) -> Result<PoemJson<PrepareRenameResponse>, PoemError> {
+    self
+      .session_set
+      .get_session(command.session_id)
+      .await?
+      .prepare_rename(command.location)
+      .await
+      .map_err(Self::request_error)?
+      .poem_json()
+      .ok()
+  }
+
+  #[oai(path = "/session/file/rename", method = "post")]
+  async fn rename(&self, PoemJson(command): PoemJson<RenameCommand>) -> Result<PoemJson<RenameResponse>, PoemError> {
+    self
+      .session_set
+      .get_session(command.session_id)
+      .await?
+      .rename(command.location, command.new_name)
+      .await
+      .map_err(Self::request_error)?
+      .poem_json()
+      .ok()
+  }
+
+  #[oai(path = "/session/file/completion", method = "post")]
+  async fn get_completions(
+    &self,
+    PoemJson(command): PoemJson<GetCompletionsCommand>,
+  ) -> Result<PoemJson<GetCompletionsResponse>, PoemError> {
+    self
+      .session_set
+      .get_session(command.session_id)
+      .await?
+      .get_completions(command.location, command.trigger_kind, command.trigger_character)
+      .await
+      .map_err(Self::request_error)?
+      .poem_json()
+      .ok()
+  }
+
+  #[oai(path = "/session/completion/resolve", method = "post")]
+  async fn resolve_completion_item(
+    &self,
+    PoemJson(command): PoemJson<ResolveCompletionItemCommand>,
+  ) -> Result<PoemJson<ResolveCompletionItemResponse>, PoemError> {
+    self
+      .session_set
+      .get_session(command.session_id)
+      .await?
+      .resolve_completion_item(command.item)
+      .await
+      .map_err(Self::request_error)?
+      .poem_json()
+      .ok()
+  }
+
+  #[oai(path = "/session/file/document-symbols", method = "post")]
+  async fn get_document_symbols(
+    &self,
+    PoemJson(command): PoemJson<GetDocumentSymbolsCommand>,
+  ) -> Result<PoemJson<GetDocumentSymbolsResponse>, PoemError> {
+    self
+      .session_set
+      .get_session(command.session_id)
+      .await?
+      .get_document_symbols(command.lean_filepath)
+      .await
+      .map_err(Self::request_error)?
+      .poem_json()
+      .ok()
+  }
+
+  #[oai(path = "/session/file/code-actions", method = "post")]
+  async fn get_code_actions(
+    &self,
+    PoemJson(command): PoemJson<GetCodeActionsCommand>,
+  ) -> Result<PoemJson<GetCodeActionsResponse>, PoemError> {
+    self
+      .session_set
+      .get_session(command.session_id)
+      .await?
+      .get_code_actions(command.range_location)
+      .await
+      .map_err(Self::request_error)?
+      .poem_json()
+      .ok()
+  }
+
+  #[oai(path = "/session/file/folding-ranges", method = "post")]
+  async fn get_folding_ranges(
+    &self,
+    PoemJson(command): PoemJson<GetFoldingRangesCommand>,
+  ) -> Result<PoemJson<GetFoldingRangesResponse>, PoemError> {
+    self
+      .session_set
+      .get_session(command.session_id)
+      .await?
+      .get_folding_ranges(command.lean_filepath)
+      .await
+      .map_err(Self::request_error)?
+      .poem_json()
+      .ok()
+  }
+
+  #[oai(path = "/session/file/call-hierarchy", method = "post")]
+  async fn get_call_hierarchy(
+    &self,
+    PoemJson(command): PoemJson<GetCallHierarchyCommand>,
+  ) -> Result<PoemJson<GetCallHierarchyResponse>, PoemError> {
+    self
+      .session_set
+      .get_session(command.session_id)
+      .await?
+      .get_call_hierarchy(command.location, command.outgoing, command.depth)
+      .await
+      .map_err(Self::request_error)?
+      .poem_json()
+      .ok()
+  }
+
+  #[oai(path = "/session/file/inlay-hints", method = "post")]
+  async fn get_inlay_hints(
+    &self,
+    PoemJson(command): PoemJson<GetInlayHintsCommand>,
+  ) -> Result<PoemJson<GetInlayHintsResponse>, PoemError> {
+    self
+      .session_set
+      .get_session(command.session_id)
       .await?
+      .get_inlay_hints(command.range_location)
+      .await
+      .map_err(Self::request_error)?
+      .poem_json()
+      .ok()
+  }
+
+  #[oai(path = "/session/file/document-highlights", method = "post")]
+  async fn get_document_highlights(
+    &self,
+    PoemJson(command): PoemJson<GetDocumentHighlightsCommand>,
+  ) -> Result<PoemJson<GetDocumentHighlightsResponse>, PoemError> {
+    self
+      .session_set
+      .get_session(command.session_id)
+      .await?
+      .get_document_highlights(command.location)
+      .await
+      .map_err(Self::request_error)?
+      .poem_json()
+      .ok()
+  }
+
+  #[oai(path = "/session/workspace/symbols", method = "post")]
+  async fn get_workspace_symbols(
+    &self,
+    PoemJson(command): PoemJson<GetWorkspaceSymbolsCommand>,
+  ) -> Result<PoemJson<GetWorkspaceSymbolsResponse>, PoemError> {
+    self
+      .session_set
+      .get_session(command.session_id)
+      .await?
+      .get_workspace_symbols(command.query, command.limit, command.kinds)
+      .await
+      .map_err(Self::request_error)?
+      .poem_json()
+      .ok()
+  }
+
+  #[oai(path = "/session/file/semantic-tokens", method = "post")]
+  async fn get_semantic_tokens(
+    &self,
+    PoemJson(command): PoemJson<GetSemanticTokensCommand>,
+  ) -> Result<PoemJson<GetSemanticTokensResponse>, PoemError> {
+    self
+      .session_set
+      .get_session(command.session_id)
+      .await?
+      .get_semantic_tokens(command.lean_filepath)
+      .await
+      .map_err(Self::request_error)?
+      .poem_json()
+      .ok()
+  }
+
+  #[oai(path = "/session/file/semantic-tokens/range", method = "post")]
+  async fn get_semantic_tokens_range(
+    &self,
+    PoemJson(command): PoemJson<GetSemanticTokensRangeCommand>,
+  ) -> Result<PoemJson<GetSemanticTokensResponse>, PoemError> {
+    self
+      .session_set
+      .get_session(command.session_id)
+      .await?
+      .get_semantic_tokens_range(command.range_location)
+      .await
+      .map_err(Self::request_error)?
+      .poem_json()
+      .ok()
+  }
+
+  #[oai(path = "/session/workspace-edit/apply", method = "post")]
+  async fn apply_workspace_edit(
+    &self,
+    PoemJson(command): PoemJson<ApplyWorkspaceEditCommand>,
+  ) -> Result<PoemJson<ApplyWorkspaceEditResponse>, PoemError> {
+    self
+      .session_set
+      .get_session(command.session_id)
+      .await?
+      .apply_workspace_edit(command.workspace_edit, command.write)
+      .await?
+      .poem_json()
+      .ok()
+  }
+
+  #[oai(path = "/session/code-action/apply", method = "post")]
+  async fn apply_code_action(
+    &self,
+    PoemJson(command): PoemJson<ApplyCodeActionCommand>,
+  ) -> Result<PoemJson<ApplyWorkspaceEditResponse>, PoemError> {
+    self
+      .session_set
+      .get_session(command.session_id)
+      .await?
+      .apply_code_action(command.action, command.write)
+      .await
+      .map_err(Self::request_error)?
+      .poem_json()
+      .ok()
+  }
+
+  #[oai(path = "/session/file/diagnostics", method = "post")]
+  async fn get_diagnostics(
+    &self,
+    PoemJson(command): PoemJson<GetDiagnosticsCommand>,
+  ) -> Result<PoemJson<GetDiagnosticsResponse>, PoemError> {
+    self
+      .session_set
+      .get_session(command.session_id)
+      .await?
+      .get_diagnostics(command.lean_filepath, command.filter)
+      .await?
+      .poem_json()
+      .ok()
+  }
+
+  #[oai(path = "/session/file/interactive-diagnostics", method = "post")]
+  async fn get_interactive_diagnostics(
+    &self,
+    PoemJson(command): PoemJson<GetInteractiveDiagnosticsCommand>,
+  ) -> Result<PoemJson<GetInteractiveDiagnosticsResponse>, PoemError> {
+    self
+      .session_set
+      .get_session(command.session_id)
+      .await?
+      .get_interactive_diagnostics(
+        command.lean_filepath,
+        command.start_line,
+        command.end_line,
+        command.keep_references,
+      )
+      .await
+      .map_err(Self::request_error)?
+      .poem_json()
+      .ok()
+  }
+
+  #[oai(path = "/session/file/interactive-diagnostics/trace-children", method = "post")]
+  async fn expand_trace_children(
+    &self,
+    PoemJson(command): PoemJson<ExpandTraceChildrenCommand>,
+  ) -> Result<PoemJson<ExpandTraceChildrenResponse>, PoemError> {
+    self
+      .session_set
+      .get_session(command.session_id)
+      .await?
+      .expand_trace_children(command.lean_filepath, command.children, command.keep_references)
+      .await
+      .map_err(Self::request_error)?
+      .poem_json()
+      .ok()
+  }
+
+  #[oai(path = "/session/file/wait", method = "post")]
+  async fn wait_for_file(
+    &self,
+    PoemJson(command): PoemJson<WaitForFileCommand>,
+  ) -> Result<PoemJson<GetDiagnosticsResponse>, PoemError> {
+    self
+      .session_set
+      .get_session(command.session_id)
+      .await?
+      .wait_for_file(
+        command.lean_filepath,
+        command.version,
+        command.timeout_ms,
+        command.filter,
+      )
+      .await
+      .map_err(Self::request_error)?
       .poem_json()
       .ok()
   }
@@ -224,12 +697,138 @@ impl Server {
       .get_session(session_id)
       .await?
       .get_plain_goals(location)
+      .await
+      .map_err(Self::request_error)?
+      .poem_json();
+
+    response.ok()
+  }
+
+  #[oai(path = "/session/info-view/plain-term-goal", method = "get")]
+  async fn get_plain_term_goal(
+    &self,
+    Query(session_id): Query<Option<Ulid>>,
+    Query(filepath): Query<PathBuf>,
+    Query(line): Query<usize>,
+    Query(character): Query<usize>,
+  ) -> Result<PoemJson<GetPlainTermGoalResponse>, PoemError> {
+    let location = Location::new(filepath, line, character);
+    let response = self
+      .session_set
+      .get_session(session_id)
+      .await?
+      .get_plain_term_goal(location)
+      .await
+      .map_err(Self::request_error)?
+      .poem_json();
+
+    response.ok()
+  }
+
+  #[oai(path = "/session/info-view/interactive-goals", method = "get")]
+  async fn get_interactive_goals(
+    &self,
+    Query(session_id): Query<Option<Ulid>>,
+    Query(filepath): Query<PathBuf>,
+    Query(line): Query<usize>,
+    Query(character): Query<usize>,
+    Query(keep_references): Query<Option<bool>>,
+  ) -> Result<PoemJson<GetInteractiveGoalsResponse>, PoemError> {
+    let location = Location::new(filepath, line, character);
+    let response = self
+      .session_set
+      .get_session(session_id)
       .await?
+      .get_interactive_goals(location, keep_references.unwrap_or_default())
+      .await
+      .map_err(Self::request_error)?
       .poem_json();
 
     response.ok()
   }
 
+  #[oai(path = "/session/server-request/reply", method = "post")]
+  async fn reply_to_server_request(
+    &self,
+    PoemJson(command): PoemJson<ReplyToServerRequestCommand>,
+  ) -> Result<PoemJson<()>, PoemError> {
+    self
+      .session_set
+      .get_session(command.session_id)
+      .await?
+      .reply_to_server_request(command.id, command.result, command.error)
+      .await?
+      .poem_json()
+      .ok()
+  }
+
+  #[oai(path = "/session/lsp/request", method = "post")]
+  async fn send_raw_request(
+    &self,
+    PoemJson(command): PoemJson<RawMessageCommand>,
+  ) -> Result<PoemJson<RawRequestResponse>, PoemError> {
+    self
+      .session_set
+      .get_session(command.session_id)
+      .await?
+      .send_raw_request(command.method, command.params)
+      .await
+      .map_err(Self::request_error)?
+      .poem_json()
+      .ok()
+  }
+
+  #[oai(path = "/session/lsp/notify", method = "post")]
+  async fn send_raw_notification(
+    &self,
+    PoemJson(command): PoemJson<RawMessageCommand>,
+  ) -> Result<PoemJson<()>, PoemError> {
+    self
+      .session_set
+      .get_session(command.session_id)
+      .await?
+      .send_raw_notification(command.method, command.params)
+      .await?
+      .poem_json()
+      .ok()
+  }
+
+  #[oai(path = "/session/lean-rpc/call", method = "post")]
+  async fn call_lean_rpc(
+    &self,
+    PoemJson(command): PoemJson<LeanRpcCallCommand>,
+  ) -> Result<PoemJson<LeanRpcCallResponse>, PoemError> {
+    self
+      .session_set
+      .get_session(command.session_id)
+      .await?
+      .call_lean_rpc(
+        command.location,
+        command.method,
+        command.params,
+        command.keep_references,
+      )
+      .await
+      .map_err(Self::request_error)?
+      .poem_json()
+      .ok()
+  }
+
+  #[oai(path = "/session/lean-rpc/release", method = "post")]
+  async fn release_lean_rpc_references(
+    &self,
+    PoemJson(command): PoemJson<LeanRpcReleaseCommand>,
+  ) -> Result<PoemJson<()>, PoemError> {
+    self
+      .session_set
+      .get_session(command.session_id)
+      .await?
+      .release_lean_rpc_references(command.lean_filepath, command.refs)
+      .await?
+      .poem_json()
+      .ok()
+  }
+
   #[allow(clippy::unused_async)]
   #[oai(path = "/stream", method = "get")]
   async fn stream(&self, web_socket: WebSocket) -> BoxWebSocketUpgraded {
@@ -6,7 +6,10 @@ use poem_openapi::Object;
 use serde::{Deserialize, Serialize};
 use ulid::Ulid;
 
-use crate::commands::ChangeFileCommand;
+use crate::{
+  commands::{ChangeFileCommand, EditFileCommand},
+  types::TextEdit,
+};
 
 #[derive(Deserialize, Object, Serialize)]
 pub struct ChangeFileRequest {
@@ -39,3 +42,36 @@ impl ChangeFileRequest {
     change_file_request.ok()
   }
 }
+
+#[derive(Deserialize, Object, Serialize)]
+pub struct EditFileRequest {
+  pub session_id: Option<Ulid>,
+  pub lean_filepath: PathBuf,
+  pub edits: Vec<TextEdit>,
+}
+
+impl EditFileRequest {
+  pub async fn new(command: EditFileCommand) -> Result<Self, AnyhowError> {
+    let session_id = command.session_id;
+    let lean_filepath = command.lean_filepath;
+    let edits_json = match command.input_filepath {
+      Some(input_filepath) => {
+        input_filepath
+          .open_async()
+          .await?
+          .buf_reader_async()
+          .read_string_async()
+          .await?
+      }
+      None => tokio::io::stdin().buf_reader_async().read_string_async().await?,
+    };
+    let edits = serde_json::from_str(&edits_json)?;
+    let edit_file_request = Self {
+      session_id,
+      lean_filepath,
+      edits,
+    };
+
+    edit_file_request.ok()
+  }
+}
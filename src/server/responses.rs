@@ -1,10 +1,16 @@
+use std::path::PathBuf;
+
 use derive_more::From;
 use poem_openapi::Object;
 use serde::{Deserialize, Serialize};
 use serde_json::Value as Json;
 use ulid::Ulid;
 
-use crate::types::{PlainGoals, SessionStatus};
+use crate::types::{
+  CallHierarchyItem, DocumentHighlight, DocumentSymbol, FileDiagnostics, FileDiff, FileRange, FoldingRange, InlayHint,
+  InteractiveDiagnostic, InteractiveGoals, InteractiveMessage, PlainGoals, PlainTermGoal, Range, SemanticToken,
+  SessionStatus, WorkspaceSymbol,
+};
 
 #[derive(From, Deserialize, Object, Serialize)]
 pub struct NewSessionResponse {
@@ -21,7 +27,131 @@ pub struct GetPlainGoalsResponse {
   pub result: Option<PlainGoals>,
 }
 
+#[derive(Deserialize, Object, Serialize)]
+pub struct GetPlainTermGoalResponse {
+  pub result: Option<PlainTermGoal>,
+}
+
+#[derive(Deserialize, From, Object, Serialize)]
+pub struct GetInteractiveGoalsResponse {
+  pub result: Option<InteractiveGoals>,
+}
+
 #[derive(Deserialize, Object, Serialize)]
 pub struct HoverFileResponse {
   pub result: Json,
 }
+
+#[derive(Deserialize, From, Object, Serialize)]
+pub struct OpenFileResponse {
+  pub lean_filepath: PathBuf,
+}
+
+#[derive(Deserialize, Object, Serialize)]
+pub struct GetFileResponse {
+  pub text: String,
+  pub version: usize,
+}
+
+#[derive(Deserialize, From, Object, Serialize)]
+pub struct GetLocationsResponse {
+  pub locations: Vec<FileRange>,
+}
+
+#[derive(Deserialize, Object, Serialize)]
+pub struct GetCompletionsResponse {
+  pub is_incomplete: bool,
+  pub items: Vec<Json>,
+}
+
+#[derive(Deserialize, From, Object, Serialize)]
+pub struct ResolveCompletionItemResponse {
+  pub item: Json,
+}
+
+#[derive(Deserialize, From, Object, Serialize)]
+pub struct GetDocumentSymbolsResponse {
+  pub symbols: Vec<DocumentSymbol>,
+}
+
+#[derive(Deserialize, From, Object, Serialize)]
+pub struct GetCodeActionsResponse {
+  pub actions: Vec<Json>,
+}
+
+#[derive(Deserialize, From, Object, Serialize)]
+pub struct GetFoldingRangesResponse {
+  pub ranges: Vec<FoldingRange>,
+}
+
+#[derive(Deserialize, Object, Serialize)]
+pub struct GetInlayHintsResponse {
+  pub filepath: PathBuf,
+  pub hints: Vec<InlayHint>,
+}
+
+#[derive(Deserialize, Object, Serialize)]
+pub struct GetDocumentHighlightsResponse {
+  pub filepath: PathBuf,
+  pub highlights: Vec<DocumentHighlight>,
+}
+
+#[derive(Deserialize, From, Object, Serialize)]
+pub struct GetCallHierarchyResponse {
+  pub items: Vec<CallHierarchyItem>,
+}
+
+#[derive(Deserialize, From, Object, Serialize)]
+pub struct GetWorkspaceSymbolsResponse {
+  pub symbols: Vec<WorkspaceSymbol>,
+}
+
+#[derive(Default, Deserialize, Object, Serialize)]
+pub struct GetSemanticTokensResponse {
+  pub result_id: Option<String>,
+  pub data: Vec<usize>,
+  pub tokens: Vec<SemanticToken>,
+}
+
+#[derive(Deserialize, Object, Serialize)]
+pub struct PrepareRenameResponse {
+  pub can_rename: bool,
+  pub range: Option<Range>,
+  pub placeholder: Option<String>,
+}
+
+#[derive(Deserialize, Object, Serialize)]
+pub struct RenameResponse {
+  pub workspace_edit: Json,
+  pub diffs: Vec<FileDiff>,
+}
+
+#[derive(Deserialize, From, Object, Serialize)]
+pub struct ApplyWorkspaceEditResponse {
+  pub diffs: Vec<FileDiff>,
+}
+
+#[derive(Deserialize, From, Object, Serialize)]
+pub struct LeanRpcCallResponse {
+  pub result: Json,
+}
+
+#[derive(Deserialize, From, Object, Serialize)]
+pub struct RawRequestResponse {
+  pub response: Json,
+}
+
+#[derive(Deserialize, From, Object, Serialize)]
+pub struct GetInteractiveDiagnosticsResponse {
+  pub diagnostics: Vec<InteractiveDiagnostic>,
+}
+
+#[derive(Deserialize, From, Object, Serialize)]
+pub struct ExpandTraceChildrenResponse {
+  pub children: Vec<InteractiveMessage>,
+}
+
+#[derive(Deserialize, From, Object, Serialize)]
+pub struct GetDiagnosticsResponse {
+  pub result: Option<FileDiagnostics>,
+}
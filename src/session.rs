@@ -1,4 +1,4 @@
-use std::path::{Path, PathBuf};
+use std::{collections::HashSet, path::PathBuf};
 
 use anyhow::Error as AnyhowError;
 use mkutils::{IntoStream, Utils};
@@ -8,10 +8,17 @@ use tokio_stream::wrappers::BroadcastStream as BroadcastReceiverStream;
 use ulid::Ulid;
 
 use crate::{
-  commands::SessionCommand,
-  server::responses::{GetPlainGoalsResponse, HoverFileResponse},
+  commands::{NewSessionCommand, SessionCommand},
+  server::responses::{
+    ApplyWorkspaceEditResponse, ExpandTraceChildrenResponse, GetCallHierarchyResponse, GetCodeActionsResponse,
+    GetCompletionsResponse, GetDiagnosticsResponse, GetDocumentHighlightsResponse, GetDocumentSymbolsResponse,
+    GetFileResponse, GetFoldingRangesResponse, GetInlayHintsResponse, GetInteractiveDiagnosticsResponse,
+    GetInteractiveGoalsResponse, GetLocationsResponse, GetPlainGoalsResponse, GetPlainTermGoalResponse,
+    GetSemanticTokensResponse, GetWorkspaceSymbolsResponse, HoverFileResponse, LeanRpcCallResponse, OpenFileResponse,
+    PrepareRenameResponse, RawRequestResponse, RenameResponse, ResolveCompletionItemResponse,
+  },
   session_runner::SessionRunner,
-  types::{Location, SessionStatus},
+  types::{CallHierarchyCall, CallHierarchyItem, DiagnosticFilter, Location, RangeLocation, SessionStatus, TextEdit},
 };
 
 #[derive(Clone)]
@@ -22,22 +29,14 @@ pub struct Session {
 }
 
 impl Session {
+  const DEFAULT_CALL_HIERARCHY_DEPTH: usize = 3;
   const NOTIFICATIONS_CAPACITY: usize = 32;
 
-  pub fn new(
-    lean_path: &Path,
-    lean_server_log_dirpath: Option<&Path>,
-  ) -> Result<(Session, SessionRunner), AnyhowError> {
+  pub fn new(command: &NewSessionCommand) -> Result<(Session, SessionRunner), AnyhowError> {
     let id = Ulid::new();
     let (commands, runner_commands) = tokio::sync::mpsc::unbounded_channel();
     let (notifications, _notifications_receiver) = tokio::sync::broadcast::channel(Self::NOTIFICATIONS_CAPACITY);
-    let session_runner = SessionRunner::new(
-      id,
-      runner_commands,
-      notifications.clone(),
-      lean_path,
-      lean_server_log_dirpath,
-    )?;
+    let session_runner = SessionRunner::new(id, runner_commands, notifications.clone(), command)?;
     let session = Self {
       id,
       commands,
@@ -53,27 +52,315 @@ impl Session {
   }
 
   pub async fn initialize(&self) -> Result<(), AnyhowError> {
-    crate::macros::run_command!(self, SessionCommand::Initialize).ok()
+    crate::macros::run_command!(self, SessionCommand::Initialize)
   }
 
-  pub async fn open_file(&self, filepath: PathBuf) -> Result<(), AnyhowError> {
-    crate::macros::run_command!(self, SessionCommand::OpenFile, filepath)
+  pub async fn open_file(
+    &self,
+    filepath: Option<PathBuf>,
+    text: Option<String>,
+  ) -> Result<OpenFileResponse, AnyhowError> {
+    crate::macros::run_command!(self, SessionCommand::OpenFile, filepath, text)
   }
 
   pub async fn change_file(&self, filepath: PathBuf, text: String) -> Result<(), AnyhowError> {
     crate::macros::run_command!(self, SessionCommand::ChangeFile, filepath, text)
   }
 
+  pub async fn edit_file(&self, filepath: PathBuf, edits: Vec<TextEdit>) -> Result<(), AnyhowError> {
+    crate::macros::run_command!(self, SessionCommand::EditFile, filepath, edits)
+  }
+
+  pub async fn get_file(&self, filepath: PathBuf) -> Result<GetFileResponse, AnyhowError> {
+    crate::macros::run_command!(self, SessionCommand::GetFile, filepath)
+  }
+
+  pub async fn save_file(&self, filepath: PathBuf) -> Result<(), AnyhowError> {
+    crate::macros::run_command!(self, SessionCommand::SaveFile, filepath)
+  }
+
   pub async fn close_file(&self, filepath: PathBuf) -> Result<(), AnyhowError> {
     crate::macros::run_command!(self, SessionCommand::CloseFile, filepath)
   }
 
   pub async fn hover_file(&self, location: Location) -> Result<HoverFileResponse, AnyhowError> {
-    crate::macros::run_command!(self, SessionCommand::HoverFile, location).ok()
+    crate::macros::run_command!(self, SessionCommand::HoverFile, location)
+  }
+
+  pub async fn get_definition(&self, location: Location) -> Result<GetLocationsResponse, AnyhowError> {
+    crate::macros::run_command!(self, SessionCommand::GetDefinition, location)
+  }
+
+  pub async fn get_declaration(&self, location: Location) -> Result<GetLocationsResponse, AnyhowError> {
+    crate::macros::run_command!(self, SessionCommand::GetDeclaration, location)
+  }
+
+  pub async fn get_type_definition(&self, location: Location) -> Result<GetLocationsResponse, AnyhowError> {
+    crate::macros::run_command!(self, SessionCommand::GetTypeDefinition, location)
+  }
+
+  pub async fn get_references(
+    &self,
+    location: Location,
+    include_declaration: bool,
+  ) -> Result<GetLocationsResponse, AnyhowError> {
+    crate::macros::run_command!(self, SessionCommand::GetReferences, location, include_declaration)
+  }
+
+  pub async fn prepare_rename(&self, location: Location) -> Result<PrepareRenameResponse, AnyhowError> {
+    crate::macros::run_command!(self, SessionCommand::PrepareRename, location)
+  }
+
+  pub async fn rename(&self, location: Location, new_name: String) -> Result<RenameResponse, AnyhowError> {
+    crate::macros::run_command!(self, SessionCommand::Rename, location, new_name)
+  }
+
+  pub async fn get_completions(
+    &self,
+    location: Location,
+    trigger_kind: Option<usize>,
+    trigger_character: Option<String>,
+  ) -> Result<GetCompletionsResponse, AnyhowError> {
+    crate::macros::run_command!(
+      self,
+      SessionCommand::GetCompletions,
+      location,
+      trigger_kind,
+      trigger_character
+    )
+  }
+
+  pub async fn resolve_completion_item(&self, item: Json) -> Result<ResolveCompletionItemResponse, AnyhowError> {
+    crate::macros::run_command!(self, SessionCommand::ResolveCompletionItem, item)
+  }
+
+  pub async fn get_document_symbols(&self, filepath: PathBuf) -> Result<GetDocumentSymbolsResponse, AnyhowError> {
+    crate::macros::run_command!(self, SessionCommand::GetDocumentSymbols, filepath)
+  }
+
+  pub async fn get_code_actions(&self, range_location: RangeLocation) -> Result<GetCodeActionsResponse, AnyhowError> {
+    crate::macros::run_command!(self, SessionCommand::GetCodeActions, range_location)
+  }
+
+  pub async fn get_folding_ranges(&self, filepath: PathBuf) -> Result<GetFoldingRangesResponse, AnyhowError> {
+    crate::macros::run_command!(self, SessionCommand::GetFoldingRanges, filepath)
+  }
+
+  async fn prepare_call_hierarchy(&self, location: Location) -> Result<Vec<CallHierarchyCall>, AnyhowError> {
+    crate::macros::run_command!(self, SessionCommand::PrepareCallHierarchy, location)
+  }
+
+  async fn get_call_hierarchy_calls(&self, item: Json, outgoing: bool) -> Result<Vec<CallHierarchyCall>, AnyhowError> {
+    crate::macros::run_command!(self, SessionCommand::GetCallHierarchyCalls, item, outgoing)
+  }
+
+  // NOTE: an item that is already being resolved further up the current path
+  // is marked as recursive rather than resolved again, which would never end,
+  // whereas items reached through several paths are resolved along each of them
+  async fn resolve_call_hierarchy_call(
+    &self,
+    call: CallHierarchyCall,
+    outgoing: bool,
+    depth: usize,
+    ancestors: &mut HashSet<(PathBuf, usize, usize)>,
+  ) -> Result<CallHierarchyItem, AnyhowError> {
+    let CallHierarchyCall { lsp_item, mut item } = call;
+    let start = &item.selection_range.start;
+    let key = (item.location.filepath.clone(), start.line, start.character);
+
+    if ancestors.contains(&key) {
+      item.is_recursive = true;
+
+      return item.ok();
+    }
+
+    if depth == 0 {
+      return item.ok();
+    }
+
+    ancestors.insert(key.clone());
+
+    for call in self.get_call_hierarchy_calls(lsp_item, outgoing).await? {
+      let call_item = Box::pin(self.resolve_call_hierarchy_call(call, outgoing, depth - 1, ancestors)).await?;
+
+      item.calls.push(call_item);
+    }
+
+    ancestors.remove(&key);
+
+    item.ok()
+  }
+
+  pub async fn get_call_hierarchy(
+    &self,
+    location: Location,
+    outgoing: bool,
+    depth: Option<usize>,
+  ) -> Result<GetCallHierarchyResponse, AnyhowError> {
+    let depth = depth.unwrap_or(Self::DEFAULT_CALL_HIERARCHY_DEPTH);
+    let mut ancestors = HashSet::new();
+    let mut items = Vec::new();
+
+    for call in self.prepare_call_hierarchy(location).await? {
+      let item = self
+        .resolve_call_hierarchy_call(call, outgoing, depth, &mut ancestors)
+        .await?;
+
+      items.push(item);
+    }
+
+    GetCallHierarchyResponse::from(items).ok()
+  }
+
+  pub async fn get_inlay_hints(&self, range_location: RangeLocation) -> Result<GetInlayHintsResponse, AnyhowError> {
+    crate::macros::run_command!(self, SessionCommand::GetInlayHints, range_location)
+  }
+
+  pub async fn get_document_highlights(
+    &self,
+    location: Location,
+  ) -> Result<GetDocumentHighlightsResponse, AnyhowError> {
+    crate::macros::run_command!(self, SessionCommand::GetDocumentHighlights, location)
+  }
+
+  pub async fn get_workspace_symbols(
+    &self,
+    query: String,
+    limit: Option<usize>,
+    kinds: Vec<usize>,
+  ) -> Result<GetWorkspaceSymbolsResponse, AnyhowError> {
+    crate::macros::run_command!(self, SessionCommand::GetWorkspaceSymbols, query, limit, kinds)
+  }
+
+  pub async fn get_semantic_tokens(&self, filepath: PathBuf) -> Result<GetSemanticTokensResponse, AnyhowError> {
+    crate::macros::run_command!(self, SessionCommand::GetSemanticTokens, filepath)
+  }
+
+  pub async fn get_semantic_tokens_range(
+    &self,
+    range_location: RangeLocation,
+  ) -> Result<GetSemanticTokensResponse, AnyhowError> {
+    crate::macros::run_command!(self, SessionCommand::GetSemanticTokensRange, range_location)
+  }
+
+  pub async fn apply_workspace_edit(
+    &self,
+    workspace_edit: Json,
+    write: bool,
+  ) -> Result<ApplyWorkspaceEditResponse, AnyhowError> {
+    crate::macros::run_command!(self, SessionCommand::ApplyWorkspaceEdit, workspace_edit, write)
+  }
+
+  pub async fn apply_code_action(&self, action: Json, write: bool) -> Result<ApplyWorkspaceEditResponse, AnyhowError> {
+    crate::macros::run_command!(self, SessionCommand::ApplyCodeAction, action, write)
   }
 
   pub async fn get_plain_goals(&self, location: Location) -> Result<GetPlainGoalsResponse, AnyhowError> {
-    crate::macros::run_command!(self, SessionCommand::GetPlainGoals, location).ok()
+    crate::macros::run_command!(self, SessionCommand::GetPlainGoals, location)
+  }
+
+  pub async fn get_plain_term_goal(&self, location: Location) -> Result<GetPlainTermGoalResponse, AnyhowError> {
+    crate::macros::run_command!(self, SessionCommand::GetPlainTermGoal, location)
+  }
+
+  pub async fn get_interactive_goals(
+    &self,
+    location: Location,
+    keep_references: bool,
+  ) -> Result<GetInteractiveGoalsResponse, AnyhowError> {
+    crate::macros::run_command!(self, SessionCommand::GetInteractiveGoals, location, keep_references)
+  }
+
+  pub async fn get_diagnostics(
+    &self,
+    filepath: PathBuf,
+    filter: DiagnosticFilter,
+  ) -> Result<GetDiagnosticsResponse, AnyhowError> {
+    crate::macros::run_command!(self, SessionCommand::GetDiagnostics, filepath, filter)
+  }
+
+  pub async fn get_interactive_diagnostics(
+    &self,
+    filepath: PathBuf,
+    start_line: Option<usize>,
+    end_line: Option<usize>,
+    keep_references: bool,
+  ) -> Result<GetInteractiveDiagnosticsResponse, AnyhowError> {
+    crate::macros::run_command!(
+      self,
+      SessionCommand::GetInteractiveDiagnostics,
+      filepath,
+      start_line,
+      end_line,
+      keep_references
+    )
+  }
+
+  pub async fn expand_trace_children(
+    &self,
+    filepath: PathBuf,
+    children: Json,
+    keep_references: bool,
+  ) -> Result<ExpandTraceChildrenResponse, AnyhowError> {
+    crate::macros::run_command!(
+      self,
+      SessionCommand::ExpandTraceChildren,
+      filepath,
+      children,
+      keep_references
+    )
+  }
+
+  pub async fn wait_for_file(
+    &self,
+    filepath: PathBuf,
+    version: Option<usize>,
+    timeout_ms: Option<u64>,
+    filter: DiagnosticFilter,
+  ) -> Result<GetDiagnosticsResponse, AnyhowError> {
+    crate::macros::run_command!(self, SessionCommand::WaitForFile, filepath, version, timeout_ms, filter)
+  }
+
+  pub async fn call_lean_rpc(
+    &self,
+    location: Location,
+    method: String,
+    params: Option<Json>,
+    keep_references: bool,
+  ) -> Result<LeanRpcCallResponse, AnyhowError> {
+    crate::macros::run_command!(
+      self,
+      SessionCommand::CallLeanRpc,
+      location,
+      method,
+      params,
+      keep_references
+    )
+  }
+
+  pub async fn release_lean_rpc_references(&self, filepath: PathBuf, refs: Vec<Json>) -> Result<(), AnyhowError> {
+    crate::macros::run_command!(self, SessionCommand::ReleaseLeanRpcReferences, filepath, refs)
+  }
+
+  pub async fn reply_to_server_request(
+    &self,
+    id: Json,
+    result: Option<Json>,
+    error: Option<Json>,
+  ) -> Result<(), AnyhowError> {
+    crate::macros::run_command!(self, SessionCommand::ReplyToServerRequest, id, result, error)
+  }
+
+  pub async fn send_raw_request(
+    &self,
+    method: String,
+    params: Option<Json>,
+  ) -> Result<RawRequestResponse, AnyhowError> {
+    crate::macros::run_command!(self, SessionCommand::SendRawRequest, method, params)
+  }
+
+  pub async fn send_raw_notification(&self, method: String, params: Option<Json>) -> Result<(), AnyhowError> {
+    crate::macros::run_command!(self, SessionCommand::SendRawNotification, method, params)
   }
 
   pub async fn status(&self) -> Result<SessionStatus, AnyhowError> {
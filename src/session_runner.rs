@@ -1,36 +1,822 @@
 use std::{
   collections::HashMap,
-  path::{Path, PathBuf},
+  path::{Component, Path, PathBuf},
+  time::Duration,
 };
 
-use anyhow::Error as AnyhowError;
+use anyhow::{Context, Error as AnyhowError};
 use mkutils::{Event, EventReceiver, EventSender, IntoStream, ToValue, Utils};
+use notify::Event as NotifyEvent;
+use serde::Deserialize;
 use serde_json::Value as Json;
 use strum::Display;
-use tokio::sync::{
-  broadcast::Sender as BroadcastSender, mpsc::UnboundedReceiver as MpscUnboundedReceiver,
-  oneshot::Sender as OneshotSender,
+use tokio::{
+  sync::{
+    broadcast::Sender as BroadcastSender, mpsc::UnboundedReceiver as MpscUnboundedReceiver,
+    oneshot::Sender as OneshotSender,
+  },
+  time::{Instant, Interval, MissedTickBehavior},
 };
 use tokio_stream::wrappers::UnboundedReceiverStream as MpscUnboundedReceiverStream;
 use ulid::Ulid;
 
 use crate::{
-  commands::SessionCommand,
-  lean_server::LeanServer,
-  messages::{Id, Message, text_document::INITIAL_TEXT_DOCUMENT_VERSION},
-  server::responses::{GetPlainGoalsResponse, HoverFileResponse},
-  types::{Location, SessionStatus},
+  commands::{NewSessionCommand, SessionCommand},
+  document::Document,
+  file_watcher::{FileChangeType, FileSystemWatcher, FileWatcher},
+  lean_server::{LeanServer, LeanServerCommand},
+  messages::{Id, Message, text_document::INITIAL_TEXT_DOCUMENT_VERSION, window, workspace},
+  request_error::{RequestError, RequestFailure},
+  server::responses::{
+    ApplyWorkspaceEditResponse, ExpandTraceChildrenResponse, GetCodeActionsResponse, GetCompletionsResponse,
+    GetDiagnosticsResponse, GetDocumentHighlightsResponse, GetDocumentSymbolsResponse, GetFileResponse,
+    GetFoldingRangesResponse, GetInlayHintsResponse, GetInteractiveDiagnosticsResponse, GetInteractiveGoalsResponse,
+    GetLocationsResponse, GetPlainGoalsResponse, GetPlainTermGoalResponse, GetSemanticTokensResponse,
+    GetWorkspaceSymbolsResponse, HoverFileResponse, LeanRpcCallResponse, OpenFileResponse, PrepareRenameResponse,
+    RawRequestResponse, RenameResponse, ResolveCompletionItemResponse,
+  },
+  tagged_text::{LspSubexprInfo, LspTaggedText},
+  types::{
+    CallHierarchyCall, CallHierarchyItem, Diagnostic, DiagnosticFilter, DocumentHighlight, DocumentSymbol,
+    FileDiagnostics, FileRange, FoldingRange, Hypothesis, InlayHint, InteractiveDiagnostic, InteractiveGoal,
+    InteractiveGoals, InteractiveMessage, Location, MessageNode, Position, Range, RangeLocation, SemanticToken,
+    SessionStatus, TextEdit, TraceNode, WorkspaceSymbol,
+  },
+  workspace_edit::{FileChange, WorkspaceChange, WorkspaceEditPlan},
 };
 
 #[derive(Display)]
 enum Request {
-  Initialize(OneshotSender<()>),
-  GetPlainGoals(OneshotSender<GetPlainGoalsResponse>),
-  Hover(OneshotSender<HoverFileResponse>),
-  TextDocumentDocumentSymbol,
-  TextDocumentDocumentCodeAction,
-  TextDocumentFoldingRange,
-  LeanRpcConnect,
+  Initialize(OneshotSender<Result<(), AnyhowError>>),
+  Reinitialize,
+  GetPlainGoals(OneshotSender<Result<GetPlainGoalsResponse, AnyhowError>>),
+  GetPlainTermGoal(OneshotSender<Result<GetPlainTermGoalResponse, AnyhowError>>),
+  Hover(OneshotSender<Result<HoverFileResponse, AnyhowError>>),
+  Raw(OneshotSender<Result<RawRequestResponse, AnyhowError>>),
+  Definition(OneshotSender<Result<GetLocationsResponse, AnyhowError>>),
+  Declaration(OneshotSender<Result<GetLocationsResponse, AnyhowError>>),
+  TypeDefinition(OneshotSender<Result<GetLocationsResponse, AnyhowError>>),
+  References(OneshotSender<Result<GetLocationsResponse, AnyhowError>>),
+  PrepareRename(OneshotSender<Result<PrepareRenameResponse, AnyhowError>>),
+  Rename(OneshotSender<Result<RenameResponse, AnyhowError>>),
+  Completion(OneshotSender<Result<GetCompletionsResponse, AnyhowError>>),
+  CompletionItemResolve(OneshotSender<Result<ResolveCompletionItemResponse, AnyhowError>>),
+  TextDocumentDocumentSymbol(OneshotSender<Result<GetDocumentSymbolsResponse, AnyhowError>>),
+  TextDocumentDocumentCodeAction(OneshotSender<Result<GetCodeActionsResponse, AnyhowError>>),
+  TextDocumentFoldingRange(OneshotSender<Result<GetFoldingRangesResponse, AnyhowError>>),
+  TextDocumentPrepareCallHierarchy(OneshotSender<Result<Vec<CallHierarchyCall>, AnyhowError>>),
+  CallHierarchyIncomingCalls(OneshotSender<Result<Vec<CallHierarchyCall>, AnyhowError>>),
+  CallHierarchyOutgoingCalls(OneshotSender<Result<Vec<CallHierarchyCall>, AnyhowError>>),
+  TextDocumentInlayHint {
+    sender: OneshotSender<Result<GetInlayHintsResponse, AnyhowError>>,
+    filepath: PathBuf,
+  },
+  TextDocumentDocumentHighlight {
+    sender: OneshotSender<Result<GetDocumentHighlightsResponse, AnyhowError>>,
+    filepath: PathBuf,
+  },
+  WorkspaceSymbol {
+    sender: OneshotSender<Result<GetWorkspaceSymbolsResponse, AnyhowError>>,
+    limit: Option<usize>,
+    kinds: Vec<usize>,
+  },
+  TextDocumentSemanticTokensFull(OneshotSender<Result<GetSemanticTokensResponse, AnyhowError>>),
+  TextDocumentSemanticTokensRange(OneshotSender<Result<GetSemanticTokensResponse, AnyhowError>>),
+  CodeActionResolve {
+    sender: OneshotSender<Result<ApplyWorkspaceEditResponse, AnyhowError>>,
+    write: bool,
+  },
+  LeanRpcConnect {
+    uri: String,
+    rpc_calls: Vec<LeanRpcCall>,
+  },
+  LeanRpcCall(LeanRpcCall),
+}
+
+impl Request {
+  fn send_error<T>(sender: OneshotSender<Result<T, AnyhowError>>, error: AnyhowError) {
+    Err(error).send_to_oneshot(sender).log_if_error().unit();
+  }
+
+  fn fail(self, failure: RequestFailure) {
+    let request = self.to_string();
+    let error = AnyhowError::new(RequestError {
+      request,
+      failure: failure.clone(),
+    });
+
+    tracing::warn!(%error, "failing request");
+
+    match self {
+      Self::Initialize(sender) => Self::send_error(sender, error),
+      Self::GetPlainGoals(sender) => Self::send_error(sender, error),
+      Self::GetPlainTermGoal(sender) => Self::send_error(sender, error),
+      Self::Hover(sender) => Self::send_error(sender, error),
+      Self::Raw(sender) => Self::send_error(sender, error),
+      Self::Definition(sender)
+      | Self::Declaration(sender)
+      | Self::TypeDefinition(sender)
+      | Self::References(sender) => Self::send_error(sender, error),
+      Self::PrepareRename(sender) => Self::send_error(sender, error),
+      Self::Rename(sender) => Self::send_error(sender, error),
+      Self::Completion(sender) => Self::send_error(sender, error),
+      Self::CompletionItemResolve(sender) => Self::send_error(sender, error),
+      Self::TextDocumentDocumentSymbol(sender) => Self::send_error(sender, error),
+      Self::TextDocumentDocumentCodeAction(sender) => Self::send_error(sender, error),
+      Self::TextDocumentFoldingRange(sender) => Self::send_error(sender, error),
+      Self::TextDocumentPrepareCallHierarchy(sender)
+      | Self::CallHierarchyIncomingCalls(sender)
+      | Self::CallHierarchyOutgoingCalls(sender) => Self::send_error(sender, error),
+      Self::TextDocumentInlayHint { sender, .. } => Self::send_error(sender, error),
+      Self::TextDocumentDocumentHighlight { sender, .. } => Self::send_error(sender, error),
+      Self::WorkspaceSymbol { sender, .. } => Self::send_error(sender, error),
+      Self::TextDocumentSemanticTokensFull(sender) | Self::TextDocumentSemanticTokensRange(sender) => {
+        Self::send_error(sender, error);
+      }
+      Self::CodeActionResolve { sender, .. } => Self::send_error(sender, error),
+      Self::LeanRpcCall(rpc_call) => rpc_call.sender.send_error(error),
+
+      // NOTE: rpc calls that wait on a connection fail along with it
+      Self::LeanRpcConnect { rpc_calls, .. } => {
+        for rpc_call in rpc_calls {
+          rpc_call.fail(failure.clone());
+        }
+      }
+
+      // explicitly name requests without senders so new variants cause a compile error.
+      Self::Reinitialize => (),
+    }
+  }
+
+  // NOTE: a closed sender means that the caller has gone away, e.g. because the
+  // http client disconnected
+  fn is_abandoned(&self) -> bool {
+    match self {
+      Self::Initialize(sender) => sender.is_closed(),
+      Self::GetPlainGoals(sender) => sender.is_closed(),
+      Self::GetPlainTermGoal(sender) => sender.is_closed(),
+      Self::Hover(sender) => sender.is_closed(),
+      Self::Raw(sender) => sender.is_closed(),
+      Self::Definition(sender)
+      | Self::Declaration(sender)
+      | Self::TypeDefinition(sender)
+      | Self::References(sender) => sender.is_closed(),
+      Self::PrepareRename(sender) => sender.is_closed(),
+      Self::Rename(sender) => sender.is_closed(),
+      Self::Completion(sender) => sender.is_closed(),
+      Self::CompletionItemResolve(sender) => sender.is_closed(),
+      Self::TextDocumentDocumentSymbol(sender) => sender.is_closed(),
+      Self::TextDocumentDocumentCodeAction(sender) => sender.is_closed(),
+      Self::TextDocumentFoldingRange(sender) => sender.is_closed(),
+      Self::TextDocumentPrepareCallHierarchy(sender)
+      | Self::CallHierarchyIncomingCalls(sender)
+      | Self::CallHierarchyOutgoingCalls(sender) => sender.is_closed(),
+      Self::TextDocumentInlayHint { sender, .. } => sender.is_closed(),
+      Self::TextDocumentDocumentHighlight { sender, .. } => sender.is_closed(),
+      Self::WorkspaceSymbol { sender, .. } => sender.is_closed(),
+      Self::TextDocumentSemanticTokensFull(sender) | Self::TextDocumentSemanticTokensRange(sender) => {
+        sender.is_closed()
+      }
+      Self::CodeActionResolve { sender, .. } => sender.is_closed(),
+      Self::LeanRpcCall(rpc_call) => rpc_call.sender.is_closed(),
+      Self::Reinitialize | Self::LeanRpcConnect { .. } => false,
+    }
+  }
+
+  // NOTE: the session depends on the responses to lifecycle requests, e.g.
+  // files are only reopened once the lean server has been reinitialized, so
+  // they are waited on for as long as the lean server is running
+  fn is_lifecycle(&self) -> bool {
+    matches!(
+      self,
+      Self::Initialize(_) | Self::Reinitialize | Self::LeanRpcConnect { .. }
+    )
+  }
+}
+
+enum LeanRpcSender {
+  Call(OneshotSender<Result<LeanRpcCallResponse, AnyhowError>>),
+  InteractiveGoals(OneshotSender<Result<GetInteractiveGoalsResponse, AnyhowError>>),
+  InteractiveDiagnostics(OneshotSender<Result<GetInteractiveDiagnosticsResponse, AnyhowError>>),
+  TraceChildren(OneshotSender<Result<ExpandTraceChildrenResponse, AnyhowError>>),
+}
+
+impl LeanRpcSender {
+  fn send_error(self, error: AnyhowError) {
+    match self {
+      Self::Call(sender) => Request::send_error(sender, error),
+      Self::InteractiveGoals(sender) => Request::send_error(sender, error),
+      Self::InteractiveDiagnostics(sender) => Request::send_error(sender, error),
+      Self::TraceChildren(sender) => Request::send_error(sender, error),
+    }
+  }
+
+  fn is_closed(&self) -> bool {
+    match self {
+      Self::Call(sender) => sender.is_closed(),
+      Self::InteractiveGoals(sender) => sender.is_closed(),
+      Self::InteractiveDiagnostics(sender) => sender.is_closed(),
+      Self::TraceChildren(sender) => sender.is_closed(),
+    }
+  }
+
+  fn send_result(self, result: Json) -> Result<(), AnyhowError> {
+    match self {
+      Self::Call(sender) => LeanRpcCallResponse::from(result).ok().send_to_oneshot(sender),
+      Self::InteractiveGoals(sender) => result
+        .to_value_from_value::<Option<LspInteractiveGoals>>()
+        .map(|lsp_interactive_goals| {
+          GetInteractiveGoalsResponse::from(lsp_interactive_goals.map(InteractiveGoals::from))
+        })
+        .context("invalid interactive goals response")
+        .send_to_oneshot(sender),
+      Self::InteractiveDiagnostics(sender) => result
+        .to_value_from_value::<Vec<LspInteractiveDiagnostic>>()
+        .map(|lsp_diagnostics| {
+          let diagnostics = lsp_diagnostics.into_iter().map(InteractiveDiagnostic::from).collect();
+
+          GetInteractiveDiagnosticsResponse { diagnostics }
+        })
+        .context("invalid interactive diagnostics response")
+        .send_to_oneshot(sender),
+      Self::TraceChildren(sender) => result
+        .to_value_from_value::<Vec<LspTaggedText<LspMsgEmbed>>>()
+        .map(|lsp_children| {
+          let children = lsp_children.into_iter().map(InteractiveMessage::from).collect();
+
+          ExpandTraceChildrenResponse { children }
+        })
+        .context("invalid trace children response")
+        .send_to_oneshot(sender),
+    }
+  }
+}
+
+struct LeanRpcCall {
+  sender: LeanRpcSender,
+  uri: String,
+  line: usize,
+  character: usize,
+  method: String,
+  params: Json,
+  keep_references: bool,
+  is_reconnected: bool,
+}
+
+impl LeanRpcCall {
+  const REQUEST_NAME: &'static str = "LeanRpcCall";
+
+  fn fail(self, failure: RequestFailure) {
+    let request = Self::REQUEST_NAME.to_owned();
+    let error = AnyhowError::new(RequestError { request, failure });
+
+    self.sender.send_error(error);
+  }
+}
+
+struct FileWaiter {
+  uri: String,
+  version: usize,
+  filter: DiagnosticFilter,
+  start_instant: Instant,
+  timeout: Duration,
+  sender: OneshotSender<Result<GetDiagnosticsResponse, AnyhowError>>,
+}
+
+impl FileWaiter {
+  const REQUEST_NAME: &'static str = "WaitForFile";
+
+  fn is_timed_out(&self) -> bool {
+    self.timeout <= self.start_instant.elapsed()
+  }
+
+  fn fail(self, failure: RequestFailure) {
+    let request = Self::REQUEST_NAME.to_owned();
+    let error = AnyhowError::new(RequestError { request, failure });
+
+    Request::send_error(self.sender, error);
+  }
+}
+
+// NOTE: what the lean server has reported about each document, which is
+// forgotten whenever a document is opened or closed so that reports about a
+// previous buffer are never mistaken for reports about the current one
+#[derive(Default)]
+struct FileReports {
+  diagnostics: HashMap<String, FileDiagnostics>,
+  progress: HashMap<String, FileProgress>,
+  waiters: Vec<FileWaiter>,
+}
+
+impl FileReports {
+  fn forget(&mut self, uri: &str) {
+    self.diagnostics.remove(uri);
+    self.progress.remove(uri);
+  }
+
+  fn forget_all(&mut self) {
+    self.diagnostics.clear();
+    self.progress.clear();
+  }
+
+  fn diagnostics(&self, uri: &str) -> impl Iterator<Item = &Diagnostic> {
+    self
+      .diagnostics
+      .get(uri)
+      .into_iter()
+      .flat_map(|file_diagnostics| &file_diagnostics.diagnostics)
+  }
+
+  fn file_diagnostics(&self, uri: &str, filter: &DiagnosticFilter) -> GetDiagnosticsResponse {
+    self
+      .diagnostics
+      .get(uri)
+      .map(|file_diagnostics| filter.filter(file_diagnostics))
+      .into()
+  }
+
+  fn store_diagnostics(&mut self, uri: String, file_diagnostics: FileDiagnostics) {
+    self.diagnostics.insert(uri, file_diagnostics);
+  }
+
+  fn update_progress(&mut self, uri: String, file_progress: FileProgress) {
+    self.progress.insert(uri, file_progress);
+    self.resolve_waiters();
+  }
+
+  fn wait(&mut self, file_waiter: FileWaiter) {
+    self.waiters.push(file_waiter);
+    self.resolve_waiters();
+  }
+
+  fn resolve_waiters(&mut self) {
+    let progress = &self.progress;
+    let (resolved_waiters, waiters) =
+      std::mem::take(&mut self.waiters)
+        .into_iter()
+        .partition::<Vec<_>, _>(|file_waiter| {
+          progress
+            .get(&file_waiter.uri)
+            .is_some_and(|file_progress| file_progress.is_complete(file_waiter.version))
+        });
+
+    self.waiters = waiters;
+
+    for file_waiter in resolved_waiters {
+      self
+        .file_diagnostics(&file_waiter.uri, &file_waiter.filter)
+        .ok()
+        .send_to_oneshot(file_waiter.sender)
+        .log_if_error()
+        .unit();
+    }
+  }
+
+  fn sweep_waiters(&mut self) {
+    let (swept_waiters, waiters) = std::mem::take(&mut self.waiters)
+      .into_iter()
+      .partition::<Vec<_>, _>(|file_waiter| file_waiter.is_timed_out() || file_waiter.sender.is_closed());
+
+    self.waiters = waiters;
+
+    for file_waiter in swept_waiters {
+      if file_waiter.is_timed_out() {
+        let timeout = file_waiter.timeout;
+
+        file_waiter.fail(RequestFailure::TimedOut { timeout });
+      }
+    }
+  }
+
+  fn fail_waiters(&mut self, failure: &RequestFailure) {
+    for file_waiter in self.waiters.drain(..) {
+      file_waiter.fail(failure.clone());
+    }
+  }
+}
+
+struct FileProgress {
+  version: Option<usize>,
+  is_processing: bool,
+}
+
+impl FileProgress {
+  fn is_complete(&self, version: usize) -> bool {
+    !self.is_processing && self.version.is_none_or(|progress_version| version <= progress_version)
+  }
+}
+
+struct PendingRequest {
+  request: Request,
+  sent_instant: Instant,
+  timeout: Option<Duration>,
+}
+
+impl PendingRequest {
+  fn new(request: Request, timeout: Option<Duration>) -> Self {
+    let sent_instant = Instant::now();
+    let timeout = timeout.filter(|_timeout| !request.is_lifecycle());
+
+    Self {
+      request,
+      sent_instant,
+      timeout,
+    }
+  }
+
+  fn is_timed_out(&self) -> bool {
+    self
+      .timeout
+      .is_some_and(|timeout| timeout <= self.sent_instant.elapsed())
+  }
+}
+
+// NOTE: a server request forwarded to subscribers, which is answered with an
+// error if none of them reply in time so that the lean server is not stalled
+struct PendingServerRequest {
+  method: String,
+  received_instant: Instant,
+}
+
+impl PendingServerRequest {
+  const REPLY_TIMEOUT: Duration = Duration::from_secs(60);
+
+  fn is_timed_out(&self) -> bool {
+    Self::REPLY_TIMEOUT <= self.received_instant.elapsed()
+  }
+}
+
+#[derive(Display)]
+#[strum(serialize_all = "snake_case")]
+enum LeanServerLifecycleEvent {
+  Restarting,
+  Restarted,
+  Stopped,
+}
+
+#[derive(Deserialize)]
+struct VersionedTextDocumentIdentifier {
+  uri: String,
+  version: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FileProgressParams {
+  text_document: VersionedTextDocumentIdentifier,
+  processing: Vec<Json>,
+}
+
+#[derive(Deserialize)]
+struct PublishDiagnosticsParams {
+  uri: String,
+  version: Option<usize>,
+  diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LspCallHierarchyItem {
+  name: String,
+  kind: usize,
+  detail: Option<String>,
+  uri: String,
+  range: Range,
+  selection_range: Range,
+}
+
+// NOTE: incoming calls name the calling item `from` and outgoing calls name the
+// called item `to`
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LspCallHierarchyCall {
+  #[serde(alias = "from", alias = "to")]
+  item: Json,
+  from_ranges: Vec<Range>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LspHypothesis {
+  names: Vec<String>,
+  r#type: LspTaggedText<LspSubexprInfo>,
+  val: Option<LspTaggedText<LspSubexprInfo>>,
+  #[serde(default)]
+  is_inserted: bool,
+  #[serde(default)]
+  is_removed: bool,
+}
+
+impl From<LspHypothesis> for Hypothesis {
+  fn from(lsp_hypothesis: LspHypothesis) -> Self {
+    Self {
+      names: lsp_hypothesis.names,
+      type_text: lsp_hypothesis.r#type.into(),
+      value_text: lsp_hypothesis.val.map(LspTaggedText::into),
+      is_inserted: lsp_hypothesis.is_inserted,
+      is_removed: lsp_hypothesis.is_removed,
+    }
+  }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LspInteractiveGoal {
+  user_name: Option<String>,
+  #[serde(default)]
+  goal_prefix: String,
+  hyps: Vec<LspHypothesis>,
+  r#type: LspTaggedText<LspSubexprInfo>,
+  #[serde(default)]
+  is_inserted: bool,
+  #[serde(default)]
+  is_removed: bool,
+}
+
+impl LspInteractiveGoal {
+  // NOTE: hygienic names look like `h._@.Module._hyg.12` and the infoview only
+  // shows the part before the macro scopes
+  const MACRO_SCOPES_SEPARATOR: &'static str = "._@.";
+}
+
+impl From<LspInteractiveGoal> for InteractiveGoal {
+  fn from(lsp_interactive_goal: LspInteractiveGoal) -> Self {
+    let case_tag = lsp_interactive_goal.user_name.as_deref().map(|user_name| {
+      user_name
+        .split(LspInteractiveGoal::MACRO_SCOPES_SEPARATOR)
+        .next()
+        .unwrap_or(user_name)
+        .to_owned()
+    });
+
+    Self {
+      user_name: lsp_interactive_goal.user_name,
+      case_tag,
+      goal_prefix: lsp_interactive_goal.goal_prefix,
+      hyps: lsp_interactive_goal.hyps.into_iter().map(Hypothesis::from).collect(),
+      target: lsp_interactive_goal.r#type.into(),
+      is_inserted: lsp_interactive_goal.is_inserted,
+      is_removed: lsp_interactive_goal.is_removed,
+    }
+  }
+}
+
+#[derive(Deserialize)]
+struct LspInteractiveGoals {
+  goals: Vec<LspInteractiveGoal>,
+}
+
+impl From<LspInteractiveGoals> for InteractiveGoals {
+  fn from(lsp_interactive_goals: LspInteractiveGoals) -> Self {
+    lsp_interactive_goals
+      .goals
+      .into_iter()
+      .map(InteractiveGoal::from)
+      .collect::<Vec<_>>()
+      .into()
+  }
+}
+
+// NOTE: widgets are shown by their fallback text, and the child of an embed
+// tag is only a placeholder
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum LspMsgEmbed {
+  Expr(LspTaggedText<LspSubexprInfo>),
+  Goal(LspInteractiveGoal),
+  Widget { alt: LspTaggedText<LspMsgEmbed> },
+  Trace(LspTraceEmbed),
+}
+
+impl LspMsgEmbed {
+  fn push_nodes(self, nodes: &mut Vec<MessageNode>) {
+    let node = match self {
+      Self::Expr(expr) => MessageNode {
+        expr: Some(expr.into()),
+        ..MessageNode::default()
+      },
+      Self::Goal(goal) => MessageNode {
+        goal: Some(goal.into()),
+        ..MessageNode::default()
+      },
+      Self::Widget { alt } => return alt.push_nodes(nodes),
+      Self::Trace(trace) => MessageNode {
+        trace: Some(trace.into()),
+        ..MessageNode::default()
+      },
+    };
+
+    nodes.push(node);
+  }
+}
+
+impl LspTaggedText<LspMsgEmbed> {
+  fn push_nodes(self, nodes: &mut Vec<MessageNode>) {
+    match self {
+      Self::Text(text) => match nodes.last_mut() {
+        Some(MessageNode {
+          text: Some(last_text), ..
+        }) => last_text.push_str(&text),
+        _node => nodes.push(MessageNode {
+          text: Some(text),
+          ..MessageNode::default()
+        }),
+      },
+      Self::Append(children) => {
+        for child in children {
+          child.push_nodes(nodes);
+        }
+      }
+      Self::Tag(embed, _child) => embed.push_nodes(nodes),
+    }
+  }
+}
+
+impl From<LspTaggedText<LspMsgEmbed>> for InteractiveMessage {
+  fn from(lsp_message: LspTaggedText<LspMsgEmbed>) -> Self {
+    let mut nodes = Vec::new();
+
+    lsp_message.push_nodes(&mut nodes);
+
+    nodes.into()
+  }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum LspTraceChildren {
+  Strict(Vec<LspTaggedText<LspMsgEmbed>>),
+  Lazy(Json),
+}
+
+#[derive(Deserialize)]
+struct LspTraceEmbed {
+  indent: usize,
+  cls: String,
+  msg: LspTaggedText<LspMsgEmbed>,
+  collapsed: bool,
+  children: LspTraceChildren,
+}
+
+impl From<LspTraceEmbed> for TraceNode {
+  fn from(lsp_trace: LspTraceEmbed) -> Self {
+    let (children, lazy_children) = match lsp_trace.children {
+      LspTraceChildren::Strict(children) => (children.into_iter().map(InteractiveMessage::from).collect(), None),
+      LspTraceChildren::Lazy(lazy_children) => (Vec::new(), lazy_children.some()),
+    };
+
+    Self {
+      class: lsp_trace.cls,
+      indent: lsp_trace.indent,
+      collapsed: lsp_trace.collapsed,
+      message: lsp_trace.msg.into(),
+      children,
+      lazy_children,
+    }
+  }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LspInteractiveDiagnostic {
+  range: Range,
+  full_range: Option<Range>,
+  severity: Option<usize>,
+  code: Option<Json>,
+  source: Option<String>,
+  message: LspTaggedText<LspMsgEmbed>,
+}
+
+impl From<LspInteractiveDiagnostic> for InteractiveDiagnostic {
+  fn from(lsp_diagnostic: LspInteractiveDiagnostic) -> Self {
+    Self {
+      range: lsp_diagnostic.range,
+      full_range: lsp_diagnostic.full_range,
+      severity: lsp_diagnostic.severity,
+      code: lsp_diagnostic.code,
+      source: lsp_diagnostic.source,
+      message: lsp_diagnostic.message.into(),
+    }
+  }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LspInlayHint {
+  position: Position,
+  label: Json,
+  kind: Option<usize>,
+  #[serde(default)]
+  padding_left: bool,
+  #[serde(default)]
+  padding_right: bool,
+  #[serde(default)]
+  text_edits: Vec<TextEdit>,
+}
+
+impl LspInlayHint {
+  // NOTE: labels are either a string or a list of label parts, which are
+  // joined here
+  fn label(&self) -> String {
+    match &self.label {
+      Json::String(label) => label.clone(),
+      Json::Array(label_parts) => label_parts
+        .iter()
+        .filter_map(|label_part| label_part.get("value").and_then(Json::as_str))
+        .collect(),
+      _label => String::new(),
+    }
+  }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SymbolInformation {
+  name: String,
+  kind: usize,
+  container_name: Option<String>,
+  location: Json,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SemanticTokensLegend {
+  token_types: Vec<String>,
+  token_modifiers: Vec<String>,
+}
+
+impl SemanticTokensLegend {
+  const SEMANTIC_TOKEN_LEN: usize = 5;
+
+  fn has_token_modifier(token_modifiers_bitset: usize, index: usize) -> bool {
+    u32::try_from(index)
+      .ok()
+      .and_then(|index| token_modifiers_bitset.checked_shr(index))
+      .is_some_and(|bits| bits & 1 == 1)
+  }
+
+  // NOTE: each token is encoded as five integers relative to the previous
+  // token, with the start character only relative to it on the same line;
+  // modifiers outside of the legend are ignored
+  fn decode(&self, data: &[usize]) -> Result<Vec<SemanticToken>, AnyhowError> {
+    if data.len() % Self::SEMANTIC_TOKEN_LEN != 0 {
+      anyhow::bail!("semantic token data of length {} is truncated", data.len());
+    }
+
+    let mut line = 0;
+    let mut start_character = 0;
+    let mut tokens = Vec::with_capacity(data.len() / Self::SEMANTIC_TOKEN_LEN);
+
+    for chunk in data.chunks_exact(Self::SEMANTIC_TOKEN_LEN) {
+      let [
+        delta_line,
+        delta_start_character,
+        length,
+        token_type_index,
+        token_modifiers_bitset,
+      ] = <[usize; Self::SEMANTIC_TOKEN_LEN]>::try_from(chunk)?;
+
+      if delta_line == 0 {
+        start_character = start_character
+          .checked_add(delta_start_character)
+          .context("semantic token start character overflows")?;
+      } else {
+        line = line.checked_add(delta_line).context("semantic token line overflows")?;
+        start_character = delta_start_character;
+      }
+
+      let token_type = self
+        .token_types
+        .get(token_type_index)
+        .with_context(|| format!("unknown semantic token type {token_type_index}"))?
+        .clone();
+      let token_modifiers = self
+        .token_modifiers
+        .iter()
+        .enumerate()
+        .filter(|(index, _token_modifier)| Self::has_token_modifier(token_modifiers_bitset, *index))
+        .map(|(_index, token_modifier)| token_modifier.clone())
+        .collect();
+      let token = SemanticToken {
+        line,
+        start_character,
+        length,
+        token_type,
+        token_modifiers,
+      };
+
+      tokens.push(token);
+    }
+
+    tokens.ok()
+  }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SemanticTokens {
+  result_id: Option<String>,
+  data: Vec<usize>,
 }
 
 pub struct SessionResult {
@@ -41,39 +827,131 @@ pub struct SessionResult {
 pub struct SessionRunner {
   id: Ulid,
   lean_server: LeanServer,
+  lean_server_log_dirpath: Option<PathBuf>,
+  lean_server_command: LeanServerCommand,
+  lean_server_start_instant: Instant,
+  lean_server_restart_instant: Option<Instant>,
+  lean_server_restart_count: usize,
+  lean_server_max_restarts: usize,
   project_dirpath: PathBuf,
   commands: MpscUnboundedReceiverStream<SessionCommand>,
-  requests: HashMap<Id, Request>,
+  requests: HashMap<Id, PendingRequest>,
+  request_timeouts: HashMap<String, Duration>,
+  default_request_timeout: Option<Duration>,
+  request_sweep_interval: Interval,
+  server_request_replies: HashMap<String, Json>,
+  pending_server_requests: HashMap<Id, PendingServerRequest>,
+  capability_registrations: HashMap<String, Json>,
+  file_system_watchers: Vec<FileSystemWatcher>,
+  file_watcher: Option<FileWatcher>,
+  auto_reload: bool,
   notifications: BroadcastSender<Json>,
-  open_file_versions: HashMap<PathBuf, usize>,
+  open_files: HashMap<PathBuf, Document>,
+  file_reports: FileReports,
+  semantic_tokens_legend: Option<SemanticTokensLegend>,
+  lean_rpc_sessions: HashMap<String, Json>,
+  lean_rpc_keep_alive_interval: Interval,
   kill_event_sender: EventSender,
   kill_event_receiver: EventReceiver,
 }
 
 impl SessionRunner {
+  const COMPLETION_TRIGGER_KIND_INVOKED: usize = 1;
+  const COMPLETION_TRIGGER_KIND_TRIGGER_CHARACTER: usize = 2;
+  const DEFAULT_LEAN_SERVER_MAX_RESTARTS: usize = 3;
+  const DEFAULT_WAIT_FOR_FILE_TIMEOUT: Duration = Duration::from_secs(600);
+  const LEAN_RPC_GET_INTERACTIVE_DIAGNOSTICS_METHOD: &'static str = "Lean.Widget.getInteractiveDiagnostics";
+  const LEAN_RPC_GET_INTERACTIVE_GOALS_METHOD: &'static str = "Lean.Widget.getInteractiveGoals";
+  const LEAN_RPC_KEEP_ALIVE_PERIOD: Duration = Duration::from_secs(10);
+  const LEAN_RPC_LAZY_TRACE_CHILDREN_METHOD: &'static str = "Lean.Widget.lazyTraceChildrenToInteractive";
+  const LEAN_RPC_NEEDS_RECONNECT_ERROR_CODE: i64 = -32900;
+  const LEAN_SERVER_RESTART_BACKOFF_INITIAL: Duration = Duration::from_secs(1);
+  const LEAN_SERVER_RESTART_BACKOFF_MAX: Duration = Duration::from_secs(30);
+  const LEAN_SERVER_RESTART_COUNT_RESET_UPTIME: Duration = Duration::from_secs(300);
   const MANIFEST_FILE_NAME: &'static str = "lake-manifest.json";
+  const METHOD_NOT_FOUND_ERROR_CODE: i64 = -32601;
+  const REQUEST_FAILED_ERROR_CODE: i64 = -32803;
+  const REQUEST_SWEEP_PERIOD: Duration = Duration::from_millis(250);
+  const VIRTUAL_FILE_DIRNAME: &'static str = ".lean-lsp";
+  const VIRTUAL_FILE_EXTENSION: &'static str = "lean";
 
   pub fn new(
     id: Ulid,
     commands: MpscUnboundedReceiver<SessionCommand>,
     notifications: BroadcastSender<Json>,
-    lean_path: &Path,
-    lean_server_log_dirpath: Option<&Path>,
+    command: &NewSessionCommand,
   ) -> Result<Self, AnyhowError> {
     let commands = commands.into_stream();
-    let project_dirpath = Self::project_dirpath(lean_path)?;
-    let lean_server = LeanServer::new(&project_dirpath, lean_server_log_dirpath)?;
+    let project_dirpath = Self::project_dirpath(&command.lean_path)?;
+    let lean_server_log_dirpath = command.lean_server_log_dirpath.clone();
+    let lean_server_command = command.lean_server_command();
+    let lean_server = LeanServer::new(
+      &project_dirpath,
+      lean_server_log_dirpath.as_deref(),
+      &lean_server_command,
+    )?;
+    let lean_server_start_instant = Instant::now();
+    let lean_server_max_restarts = command
+      .lean_server_max_restarts
+      .unwrap_or(Self::DEFAULT_LEAN_SERVER_MAX_RESTARTS);
     let requests = HashMap::default();
-    let open_file_versions = HashMap::new();
+    let mut request_timeouts = HashMap::new();
+    let mut default_request_timeout = None;
+    let mut request_sweep_interval = tokio::time::interval(Self::REQUEST_SWEEP_PERIOD);
+    let server_request_replies = command
+      .server_request_replies
+      .iter()
+      .map(|server_request_reply| (server_request_reply.method.clone(), server_request_reply.result.clone()))
+      .collect();
+    let pending_server_requests = HashMap::new();
+    let capability_registrations = HashMap::new();
+    let file_system_watchers = Vec::new();
+    let auto_reload = command.auto_reload;
+    let open_files = HashMap::new();
+    let file_reports = FileReports::default();
+    let lean_rpc_sessions = HashMap::new();
+    let mut lean_rpc_keep_alive_interval = tokio::time::interval(Self::LEAN_RPC_KEEP_ALIVE_PERIOD);
+
+    for request_timeout in &command.request_timeouts {
+      let timeout = Duration::from_millis(request_timeout.timeout_ms);
+
+      match &request_timeout.method {
+        Some(method) => request_timeouts.insert(method.clone(), timeout).unit(),
+        None => default_request_timeout = timeout.some(),
+      }
+    }
+
+    request_sweep_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    lean_rpc_keep_alive_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
     let (kill_event_sender, kill_event_receiver) = Event::new();
     let session_runner = Self {
       id,
       lean_server,
+      lean_server_log_dirpath,
+      lean_server_command,
+      lean_server_start_instant,
+      lean_server_restart_instant: None,
+      lean_server_restart_count: 0,
+      lean_server_max_restarts,
       project_dirpath,
       commands,
       requests,
+      request_timeouts,
+      default_request_timeout,
+      request_sweep_interval,
+      server_request_replies,
+      pending_server_requests,
+      capability_registrations,
+      file_system_watchers,
+      file_watcher: None,
+      auto_reload,
       notifications,
-      open_file_versions,
+      open_files,
+      file_reports,
+      semantic_tokens_legend: None,
+      lean_rpc_sessions,
+      lean_rpc_keep_alive_interval,
       kill_event_sender,
       kill_event_receiver,
     };
@@ -97,114 +975,1352 @@ impl SessionRunner {
     anyhow::bail!("unable to get project dirpath: no manifest file found in ancestor dirpaths");
   }
 
+  fn lean_server_restart_backoff(restart_count: usize) -> Duration {
+    let multiplier = 2_u32.saturating_pow(restart_count.try_into().unwrap_or(u32::MAX));
+
+    Self::LEAN_SERVER_RESTART_BACKOFF_INITIAL
+      .saturating_mul(multiplier)
+      .min(Self::LEAN_SERVER_RESTART_BACKOFF_MAX)
+  }
+
+  fn send_lean_server_lifecycle_notification(&self, event: &LeanServerLifecycleEvent, exit_status: Option<&str>) {
+    let notification =
+      Message::lean_lsp_server_lifecycle_notification(&event.to_string(), self.lean_server_restart_count, exit_status);
+
+    self.notifications.send(notification).log_if_error().unit()
+  }
+
+  // NOTE: failures to send are reported through the request rather than
+  // returned so that callers receive a typed error
   fn send_request(&mut self, request_message: Message, request: Request) -> Result<(), AnyhowError> {
-    if self.requests.insert(request_message.id.clone(), request).is_some() {
+    if let Err(error) = self.lean_server.send(request_message.json) {
+      let reason = error.to_string();
+
+      request.fail(RequestFailure::LeanServerUnavailable { reason });
+
+      return ().ok();
+    }
+
+    let timeout = self
+      .request_timeouts
+      .get(&request_message.method)
+      .copied()
+      .or(self.default_request_timeout);
+    let pending_request = PendingRequest::new(request, timeout);
+
+    if self
+      .requests
+      .insert(request_message.id.clone(), pending_request)
+      .is_some()
+    {
       tracing::warn!(id = %request_message.id, "registering request with existing id");
     }
 
-    self.lean_server.send(request_message.json)
+    ().ok()
+  }
+
+  // NOTE: a request whose message could not be built is answered with the error
+  // rather than the error being returned, which would drop the sender and leave
+  // the caller without a reply
+  fn try_send_request<T>(
+    &mut self,
+    request_message: Result<Message, AnyhowError>,
+    sender: OneshotSender<Result<T, AnyhowError>>,
+    request_fn: impl FnOnce(OneshotSender<Result<T, AnyhowError>>) -> Request,
+  ) -> Result<(), AnyhowError> {
+    match request_message {
+      Ok(request_message) => self.send_request(request_message, request_fn(sender)),
+      Err(error) => Request::send_error(sender, error).ok(),
+    }
+  }
+
+  fn fail_requests(&mut self, failure: &RequestFailure) {
+    for (_id, pending_request) in self.requests.drain() {
+      pending_request.request.fail(failure.clone());
+    }
+
+    self.file_reports.fail_waiters(failure);
+  }
+
+  // NOTE: cancels requests that have either timed out or whose callers are no
+  // longer waiting on them
+  #[tracing::instrument(skip_all)]
+  fn sweep_requests(&mut self) {
+    let swept_ids = self
+      .requests
+      .iter()
+      .filter(|(_id, pending_request)| pending_request.is_timed_out() || pending_request.request.is_abandoned())
+      .map(|(id, _pending_request)| id.clone())
+      .collect::<Vec<_>>();
+
+    for id in swept_ids {
+      let Some(pending_request) = self.requests.remove(&id) else { continue };
+      let cancel_request_notification = Message::cancel_request_notification(&id);
+
+      self.lean_server.send(cancel_request_notification).log_if_error().unit();
+
+      match pending_request.timeout {
+        Some(timeout) if pending_request.is_timed_out() => {
+          pending_request.request.fail(RequestFailure::TimedOut { timeout });
+        }
+        _ => tracing::info!(%id, request = %pending_request.request, "cancelled abandoned request"),
+      }
+    }
+
+    self.file_reports.sweep_waiters();
+    self.sweep_server_requests();
+  }
+
+  fn sweep_server_requests(&mut self) {
+    let timed_out_ids = self
+      .pending_server_requests
+      .iter()
+      .filter(|(_id, pending_server_request)| pending_server_request.is_timed_out())
+      .map(|(id, _pending_server_request)| id.clone())
+      .collect::<Vec<_>>();
+
+    for id in timed_out_ids {
+      let Some(pending_server_request) = self.pending_server_requests.remove(&id) else { continue };
+      let message = format!(
+        "no reply to {} within {:?}",
+        pending_server_request.method,
+        PendingServerRequest::REPLY_TIMEOUT
+      );
+
+      tracing::warn!(%id, method = pending_server_request.method, "server request timed out");
+
+      self
+        .fail_server_request(&id, Self::REQUEST_FAILED_ERROR_CODE, &message)
+        .log_if_error()
+        .unit();
+    }
+  }
+
+  fn initialize(&mut self, sender: OneshotSender<Result<(), AnyhowError>>) -> Result<(), AnyhowError> {
+    let request_message = self.lean_server.initialize_request();
+
+    self.try_send_request(request_message, sender, Request::Initialize)
+  }
+
+  #[tracing::instrument(skip_all)]
+  async fn process_lean_server_exit(&mut self) -> Result<(), AnyhowError> {
+    let exit_status = self.lean_server.wait().await?.to_string();
+
+    self.fail_requests(&RequestFailure::LeanServerExited {
+      exit_status: exit_status.clone(),
+    });
+
+    if Self::LEAN_SERVER_RESTART_COUNT_RESET_UPTIME <= self.lean_server_start_instant.elapsed() {
+      self.lean_server_restart_count = 0;
+    }
+
+    self.schedule_lean_server_restart(&exit_status)
+  }
+
+  // NOTE: a lean server that exits and one that cannot be started both count as
+  // a failed attempt, so that the session stops after too many of either
+  fn schedule_lean_server_restart(&mut self, reason: &str) -> Result<(), AnyhowError> {
+    if self.lean_server_max_restarts <= self.lean_server_restart_count {
+      self.send_lean_server_lifecycle_notification(&LeanServerLifecycleEvent::Stopped, reason.some());
+
+      anyhow::bail!(
+        "lean server stopped after {restart_count} restarts: {reason}",
+        restart_count = self.lean_server_restart_count,
+      );
+    }
+
+    let backoff = Self::lean_server_restart_backoff(self.lean_server_restart_count);
+
+    tracing::warn!(
+      reason,
+      ?backoff,
+      restart_count = self.lean_server_restart_count,
+      "restarting lean server"
+    );
+
+    self.lean_server_restart_instant = (Instant::now() + backoff).some();
+    self.send_lean_server_lifecycle_notification(&LeanServerLifecycleEvent::Restarting, reason.some());
+
+    ().ok()
+  }
+
+  #[tracing::instrument(skip_all)]
+  fn restart_lean_server(&mut self) -> Result<(), AnyhowError> {
+    self.lean_server_restart_instant = None;
+    self.lean_server_restart_count += 1;
+
+    let lean_server_res = LeanServer::new(
+      &self.project_dirpath,
+      self.lean_server_log_dirpath.as_deref(),
+      &self.lean_server_command,
+    );
+
+    self.lean_server = match lean_server_res {
+      Ok(lean_server) => lean_server,
+      Err(error) => {
+        let reason = format!("unable to start lean server: {error:#}");
+
+        self.fail_requests(&RequestFailure::LeanServerUnavailable { reason: reason.clone() });
+
+        return self.schedule_lean_server_restart(&reason);
+      }
+    };
+    self.lean_server_start_instant = Instant::now();
+    self.pending_server_requests.clear();
+    self.capability_registrations.clear();
+    self.lean_rpc_sessions.clear();
+    self.update_file_system_watchers()?;
+
+    let request_message = self.lean_server.initialize_request()?;
+
+    self.send_request(request_message, Request::Reinitialize)
+  }
+
+  // NOTE: replayed documents get a version bump so that notifications from the
+  // restarted server can be told apart from those of the previous one, and
+  // anything the previous server reported is dropped
+  fn reopen_files(&mut self) -> Result<(), AnyhowError> {
+    let mut uris = Vec::with_capacity(self.open_files.len());
+
+    self.file_reports.forget_all();
+
+    for (filepath, document) in &mut self.open_files {
+      let uri = filepath.to_uri()?;
+
+      document.version += 1;
+
+      let text_document_did_open_notification =
+        Message::text_document_did_open_notification(&document.text, &uri, document.version);
+
+      self.lean_server.send(text_document_did_open_notification)?;
+
+      uris.push(uri);
+    }
+
+    for uri in uris {
+      self.connect_lean_rpc_session(uri, Vec::new())?;
+    }
+
+    ().ok()
+  }
+
+  // NOTE: virtual documents are never read from disk, but must live inside the
+  // project so that the lean server resolves their imports against it;
+  // relative filepaths are relative to the project rather than to the current
+  // dirpath, and are stored resolved so that lookups by absolute filepath find
+  // them
+  fn virtual_filepath(project_dirpath: &Path, filepath: Option<PathBuf>) -> Result<PathBuf, AnyhowError> {
+    let Some(filepath) = filepath else {
+      let filepath = project_dirpath
+        .join(Self::VIRTUAL_FILE_DIRNAME)
+        .join(Ulid::new().to_string())
+        .with_extension(Self::VIRTUAL_FILE_EXTENSION);
+
+      return filepath.ok();
+    };
+    let filepath = project_dirpath.join(filepath).components().collect::<PathBuf>();
+    let is_in_project = filepath.starts_with(project_dirpath)
+      && !filepath.components().any(|component| component == Component::ParentDir);
+
+    if !is_in_project {
+      anyhow::bail!("virtual file {} is not inside the project", filepath.display());
+    }
+
+    filepath.ok()
+  }
+
+  #[tracing::instrument(skip_all)]
+  async fn open_file(
+    &mut self,
+    filepath: Option<PathBuf>,
+    text: Option<String>,
+  ) -> Result<OpenFileResponse, AnyhowError> {
+    let (filepath, text) = match (filepath, text) {
+      (Some(filepath), None) => {
+        let text = filepath
+          .open_async()
+          .await?
+          .buf_reader_async()
+          .read_string_async()
+          .await?;
+
+        (filepath, text)
+      }
+      (filepath, Some(text)) => (Self::virtual_filepath(&self.project_dirpath, filepath)?, text),
+      (None, None) => anyhow::bail!("either a lean filepath or text is required to open a file"),
+    };
+
+    if self.open_files.contains_key(&filepath) {
+      anyhow::bail!("file {} is already open", filepath.display());
+    }
+
+    let uri = filepath.to_uri()?;
+    let text_document_did_open_notification =
+      Message::text_document_did_open_notification(&text, &uri, INITIAL_TEXT_DOCUMENT_VERSION);
+
+    self.lean_server.send(text_document_did_open_notification)?;
+    self.file_reports.forget(&uri);
+    self.connect_lean_rpc_session(uri, Vec::new())?;
+
+    self.open_files.insert(filepath.clone(), Document::new(text));
+    self.update_file_watcher()?;
+
+    OpenFileResponse::from(filepath).ok()
+  }
+
+  #[tracing::instrument(skip_all)]
+  fn change_file(&mut self, filepath: &Path, text: String) -> Result<(), AnyhowError> {
+    let document = self
+      .open_files
+      .get_mut(filepath)
+      .context_path("file is not open", filepath)?;
+    let new_version = document.version + 1;
+
+    let uri = filepath.to_uri()?;
+    let text_document_did_change_notification =
+      Message::text_document_did_change_notification(&text, &uri, new_version);
+
+    self.lean_server.send(text_document_did_change_notification)?;
+
+    // only update the document if the request was successfully sent
+    document.text = text;
+    document.version = new_version;
+
+    ().ok()
+  }
+
+  #[tracing::instrument(skip_all)]
+  fn edit_file(&mut self, filepath: &Path, edits: &[TextEdit]) -> Result<(), AnyhowError> {
+    let document = self
+      .open_files
+      .get_mut(filepath)
+      .context_path("file is not open", filepath)?;
+    let new_version = document.version + 1;
+    let new_text = document.edited_text(edits)?;
+
+    let uri = filepath.to_uri()?;
+    let text_document_did_change_edits_notification =
+      Message::text_document_did_change_edits_notification(edits, &uri, new_version);
+
+    self.lean_server.send(text_document_did_change_edits_notification)?;
+
+    // only update the document if the request was successfully sent
+    document.text = new_text;
+    document.version = new_version;
+
+    ().ok()
+  }
+
+  fn get_file(&self, filepath: &Path) -> Result<GetFileResponse, AnyhowError> {
+    let document = self
+      .open_files
+      .get(filepath)
+      .context_path("file is not open", filepath)?;
+    let text = document.text.clone();
+    let version = document.version;
+
+    GetFileResponse { text, version }.ok()
+  }
+
+  #[tracing::instrument(skip_all)]
+  async fn save_file(&mut self, filepath: &Path) -> Result<(), AnyhowError> {
+    let document = self
+      .open_files
+      .get(filepath)
+      .context_path("file is not open", filepath)?;
+    let uri = filepath.to_uri()?;
+
+    // NOTE: the directory of a virtual document only exists once one is saved
+    if let Some(dirpath) = filepath.parent() {
+      tokio::fs::create_dir_all(dirpath)
+        .await
+        .context("unable to create directory")?;
+    }
+
+    tokio::fs::write(filepath, &document.text)
+      .await
+      .context("unable to write file")?;
+
+    let text_document_did_save_notification = Message::text_document_did_save_notification(&document.text, &uri);
+
+    self.lean_server.send(text_document_did_save_notification)?;
+
+    ().ok()
+  }
+
+  #[tracing::instrument(skip_all)]
+  fn close_file(&mut self, filepath: &Path) -> Result<(), AnyhowError> {
+    if !self.open_files.contains_key(filepath) {
+      anyhow::bail!("file {} is not open", filepath.display());
+    }
+
+    let uri = filepath.to_uri()?;
+    let text_document_did_close_notification = Message::text_document_did_close_notification(&uri);
+
+    self.lean_server.send(text_document_did_close_notification)?;
+
+    self.open_files.remove(filepath);
+    self.file_reports.forget(&uri);
+    self.lean_rpc_sessions.remove(&uri);
+
+    self.update_file_watcher()
+  }
+
+  #[tracing::instrument(skip_all)]
+  fn hover_file(
+    &mut self,
+    sender: OneshotSender<Result<HoverFileResponse, AnyhowError>>,
+    location: &Location,
+  ) -> Result<(), AnyhowError> {
+    let message = location
+      .filepath
+      .to_uri()
+      .map(|uri| Message::text_document_hover_request(&uri, location.line, location.character));
+
+    self.try_send_request(message, sender, Request::Hover)
+  }
+
+  #[tracing::instrument(skip_all)]
+  fn get_locations(
+    &mut self,
+    sender: OneshotSender<Result<GetLocationsResponse, AnyhowError>>,
+    location: &Location,
+    message_fn: fn(&str, usize, usize) -> Message,
+    request_fn: fn(OneshotSender<Result<GetLocationsResponse, AnyhowError>>) -> Request,
+  ) -> Result<(), AnyhowError> {
+    let message = location
+      .filepath
+      .to_uri()
+      .map(|uri| message_fn(&uri, location.line, location.character));
+
+    self.try_send_request(message, sender, request_fn)
+  }
+
+  #[tracing::instrument(skip_all)]
+  fn get_references(
+    &mut self,
+    sender: OneshotSender<Result<GetLocationsResponse, AnyhowError>>,
+    location: &Location,
+    include_declaration: bool,
+  ) -> Result<(), AnyhowError> {
+    let message = location.filepath.to_uri().map(|uri| {
+      Message::text_document_references_request(&uri, location.line, location.character, include_declaration)
+    });
+
+    self.try_send_request(message, sender, Request::References)
+  }
+
+  // NOTE: filepaths inside the project are made relative to it
+  fn project_filepath(&self, filepath: PathBuf) -> PathBuf {
+    match filepath.strip_prefix(&self.project_dirpath) {
+      Ok(relative_filepath) => relative_filepath.to_path_buf(),
+      Err(_error) => filepath,
+    }
+  }
+
+  // NOTE: accepts both locations and location links, preferring the target
+  // selection range of the latter as it covers just the name of the target
+  fn file_range(&self, location: &Json) -> Result<FileRange, AnyhowError> {
+    let uri = location
+      .get("targetUri")
+      .or_else(|| location.get("uri"))
+      .and_then(Json::as_str)
+      .context("location has no uri")?;
+    let range = location
+      .get("targetSelectionRange")
+      .or_else(|| location.get("range"))
+      .context("location has no range")?
+      .to_value_from_value::<Range>()?;
+    let filepath = self.project_filepath(crate::uri::filepath(uri)?);
+
+    FileRange { filepath, range }.ok()
+  }
+
+  fn locations_response(&self, response: &Json) -> Result<GetLocationsResponse, AnyhowError> {
+    let locations = match response.get("result").unwrap_or(&Json::Null) {
+      Json::Null => Vec::new(),
+      Json::Array(locations) => locations.iter().collect(),
+      location => vec![location],
+    };
+
+    locations
+      .into_iter()
+      .map(|location| self.file_range(location))
+      .collect::<Result<Vec<_>, _>>()?
+      .convert::<GetLocationsResponse>()
+      .ok()
+  }
+
+  #[tracing::instrument(skip_all)]
+  fn prepare_rename(
+    &mut self,
+    sender: OneshotSender<Result<PrepareRenameResponse, AnyhowError>>,
+    location: &Location,
+  ) -> Result<(), AnyhowError> {
+    let message = location
+      .filepath
+      .to_uri()
+      .map(|uri| Message::text_document_prepare_rename_request(&uri, location.line, location.character));
+
+    self.try_send_request(message, sender, Request::PrepareRename)
+  }
+
+  // NOTE: the result is either null if nothing can be renamed at the position,
+  // a range, a range with a placeholder, or a flag to use the default behavior
+  fn prepare_rename_response(response: &Json) -> Result<PrepareRenameResponse, AnyhowError> {
+    let result = response.get("result").unwrap_or(&Json::Null);
+    let can_rename = !result.is_null();
+    let range = result
+      .get("range")
+      .or_else(|| result.get("start").map(|_start| result))
+      .map(|range| range.to_value_from_value::<Range>())
+      .transpose()?;
+    let placeholder = result.get("placeholder").and_then(Json::as_str).map(str::to_owned);
+
+    PrepareRenameResponse {
+      can_rename,
+      range,
+      placeholder,
+    }
+    .ok()
+  }
+
+  #[tracing::instrument(skip_all)]
+  fn rename(
+    &mut self,
+    sender: OneshotSender<Result<RenameResponse, AnyhowError>>,
+    location: &Location,
+    new_name: &str,
+  ) -> Result<(), AnyhowError> {
+    let message = location
+      .filepath
+      .to_uri()
+      .map(|uri| Message::text_document_rename_request(&uri, location.line, location.character, new_name));
+
+    self.try_send_request(message, sender, Request::Rename)
+  }
+
+  // NOTE: the rename is only previewed here and applied separately, as its
+  // workspace edit is returned along with its diffs
+  async fn rename_response(&mut self, response: &Json) -> Result<RenameResponse, AnyhowError> {
+    let workspace_edit = response.get("result").cloned().unwrap_or_default();
+    let diffs = Self::workspace_edit_plan(&self.open_files, &workspace_edit)
+      .await?
+      .diffs(&self.project_dirpath);
+
+    RenameResponse { workspace_edit, diffs }.ok()
+  }
+
+  #[tracing::instrument(skip_all)]
+  fn get_completions(
+    &mut self,
+    sender: OneshotSender<Result<GetCompletionsResponse, AnyhowError>>,
+    location: &Location,
+    trigger_kind: Option<usize>,
+    trigger_character: Option<&str>,
+  ) -> Result<(), AnyhowError> {
+    let default_trigger_kind = match trigger_character {
+      Some(_trigger_character) => Self::COMPLETION_TRIGGER_KIND_TRIGGER_CHARACTER,
+      None => Self::COMPLETION_TRIGGER_KIND_INVOKED,
+    };
+    let trigger_kind = trigger_kind.unwrap_or(default_trigger_kind);
+    let message = location.filepath.to_uri().map(|uri| {
+      Message::text_document_completion_request(
+        &uri,
+        location.line,
+        location.character,
+        trigger_kind,
+        trigger_character,
+      )
+    });
+
+    self.try_send_request(message, sender, Request::Completion)
+  }
+
+  // NOTE: the result is either a completion list or just its items
+  fn completions_response(response: &Json) -> Result<GetCompletionsResponse, AnyhowError> {
+    let (is_incomplete, items) = match response.get("result").unwrap_or(&Json::Null) {
+      Json::Null => (false, Vec::new()),
+      Json::Array(items) => (false, items.clone()),
+      completion_list => {
+        let is_incomplete = completion_list
+          .get("isIncomplete")
+          .and_then(Json::as_bool)
+          .unwrap_or_default();
+        let items = completion_list
+          .get("items")
+          .and_then(Json::as_array)
+          .context("completion list has no items")?
+          .clone();
+
+        (is_incomplete, items)
+      }
+    };
+
+    GetCompletionsResponse { is_incomplete, items }.ok()
+  }
+
+  #[tracing::instrument(skip_all)]
+  fn resolve_completion_item(
+    &mut self,
+    sender: OneshotSender<Result<ResolveCompletionItemResponse, AnyhowError>>,
+    item: &Json,
+  ) -> Result<(), AnyhowError> {
+    let message = Message::completion_item_resolve_request(item);
+    let request = Request::CompletionItemResolve(sender);
+
+    self.send_request(message, request)
+  }
+
+  #[tracing::instrument(skip_all)]
+  fn get_document_symbols(
+    &mut self,
+    sender: OneshotSender<Result<GetDocumentSymbolsResponse, AnyhowError>>,
+    filepath: &Path,
+  ) -> Result<(), AnyhowError> {
+    let message = filepath
+      .to_uri()
+      .map(|uri| Message::text_document_document_symbol_request(&uri));
+
+    self.try_send_request(message, sender, Request::TextDocumentDocumentSymbol)
+  }
+
+  #[tracing::instrument(skip_all)]
+  fn get_code_actions(
+    &mut self,
+    sender: OneshotSender<Result<GetCodeActionsResponse, AnyhowError>>,
+    range_location: &RangeLocation,
+  ) -> Result<(), AnyhowError> {
+    let range = range_location.range();
+    let message = range_location.filepath.to_uri().map(|uri| {
+      let diagnostics = self
+        .file_reports
+        .diagnostics(&uri)
+        .filter(|diagnostic| diagnostic.range.overlaps(&range))
+        .cloned()
+        .collect::<Vec<_>>();
+
+      Message::text_document_document_code_action_request(&uri, &range, &diagnostics)
+    });
+
+    self.try_send_request(message, sender, Request::TextDocumentDocumentCodeAction)
+  }
+
+  #[tracing::instrument(skip_all)]
+  fn get_folding_ranges(
+    &mut self,
+    sender: OneshotSender<Result<GetFoldingRangesResponse, AnyhowError>>,
+    filepath: &Path,
+  ) -> Result<(), AnyhowError> {
+    let message = filepath
+      .to_uri()
+      .map(|uri| Message::text_document_folding_range_request(&uri));
+
+    self.try_send_request(message, sender, Request::TextDocumentFoldingRange)
+  }
+
+  #[tracing::instrument(skip_all)]
+  fn prepare_call_hierarchy(
+    &mut self,
+    sender: OneshotSender<Result<Vec<CallHierarchyCall>, AnyhowError>>,
+    location: &Location,
+  ) -> Result<(), AnyhowError> {
+    let message = location
+      .filepath
+      .to_uri()
+      .map(|uri| Message::text_document_prepare_call_hierarchy_request(&uri, location.line, location.character));
+
+    self.try_send_request(message, sender, Request::TextDocumentPrepareCallHierarchy)
+  }
+
+  #[tracing::instrument(skip_all)]
+  fn get_call_hierarchy_calls(
+    &mut self,
+    sender: OneshotSender<Result<Vec<CallHierarchyCall>, AnyhowError>>,
+    item: &Json,
+    outgoing: bool,
+  ) -> Result<(), AnyhowError> {
+    let (message, request) = if outgoing {
+      (
+        Message::call_hierarchy_outgoing_calls_request(item),
+        Request::CallHierarchyOutgoingCalls(sender),
+      )
+    } else {
+      (
+        Message::call_hierarchy_incoming_calls_request(item),
+        Request::CallHierarchyIncomingCalls(sender),
+      )
+    };
+
+    self.send_request(message, request)
+  }
+
+  fn call_hierarchy_call(&self, lsp_item: Json, from_ranges: Vec<Range>) -> Result<CallHierarchyCall, AnyhowError> {
+    let lsp_call_hierarchy_item = lsp_item.to_value_from_value::<LspCallHierarchyItem>()?;
+    let filepath = self.project_filepath(crate::uri::filepath(&lsp_call_hierarchy_item.uri)?);
+    let location = FileRange {
+      filepath,
+      range: lsp_call_hierarchy_item.range,
+    };
+    let item = CallHierarchyItem {
+      name: lsp_call_hierarchy_item.name,
+      kind: lsp_call_hierarchy_item.kind,
+      detail: lsp_call_hierarchy_item.detail,
+      location,
+      selection_range: lsp_call_hierarchy_item.selection_range,
+      from_ranges,
+      calls: Vec::new(),
+      is_recursive: false,
+    };
+
+    CallHierarchyCall { lsp_item, item }.ok()
+  }
+
+  fn prepare_call_hierarchy_response(&self, response: &Json) -> Result<Vec<CallHierarchyCall>, AnyhowError> {
+    response
+      .get("result")
+      .unwrap_or(&Json::Null)
+      .to_value_from_value::<Option<Vec<Json>>>()?
+      .unwrap_or_default()
+      .into_iter()
+      .map(|lsp_item| self.call_hierarchy_call(lsp_item, Vec::new()))
+      .collect()
+  }
+
+  fn call_hierarchy_calls_response(&self, response: &Json) -> Result<Vec<CallHierarchyCall>, AnyhowError> {
+    response
+      .get("result")
+      .unwrap_or(&Json::Null)
+      .to_value_from_value::<Option<Vec<LspCallHierarchyCall>>>()?
+      .unwrap_or_default()
+      .into_iter()
+      .map(|lsp_call| self.call_hierarchy_call(lsp_call.item, lsp_call.from_ranges))
+      .collect()
+  }
+
+  #[tracing::instrument(skip_all)]
+  fn get_inlay_hints(
+    &mut self,
+    sender: OneshotSender<Result<GetInlayHintsResponse, AnyhowError>>,
+    range_location: &RangeLocation,
+  ) -> Result<(), AnyhowError> {
+    let filepath = match range_location.filepath.absolute() {
+      Ok(filepath) => self.project_filepath(filepath.to_path_buf()),
+      Err(error) => return Request::send_error(sender, error).ok(),
+    };
+    let message = range_location
+      .filepath
+      .to_uri()
+      .map(|uri| Message::text_document_inlay_hint_request(&uri, &range_location.range()));
+
+    self.try_send_request(message, sender, |sender| Request::TextDocumentInlayHint {
+      sender,
+      filepath,
+    })
+  }
+
+  fn inlay_hints_response(response: &Json, filepath: PathBuf) -> Result<GetInlayHintsResponse, AnyhowError> {
+    let hints = response
+      .get("result")
+      .unwrap_or(&Json::Null)
+      .to_value_from_value::<Option<Vec<LspInlayHint>>>()?
+      .unwrap_or_default()
+      .into_iter()
+      .map(|lsp_inlay_hint| InlayHint {
+        label: lsp_inlay_hint.label(),
+        position: lsp_inlay_hint.position,
+        kind: lsp_inlay_hint.kind,
+        padding_left: lsp_inlay_hint.padding_left,
+        padding_right: lsp_inlay_hint.padding_right,
+        text_edits: lsp_inlay_hint.text_edits,
+      })
+      .collect();
+
+    GetInlayHintsResponse { filepath, hints }.ok()
+  }
+
+  #[tracing::instrument(skip_all)]
+  fn get_document_highlights(
+    &mut self,
+    sender: OneshotSender<Result<GetDocumentHighlightsResponse, AnyhowError>>,
+    location: &Location,
+  ) -> Result<(), AnyhowError> {
+    let filepath = match location.filepath.absolute() {
+      Ok(filepath) => self.project_filepath(filepath.to_path_buf()),
+      Err(error) => return Request::send_error(sender, error).ok(),
+    };
+    let message = location
+      .filepath
+      .to_uri()
+      .map(|uri| Message::text_document_document_highlight_request(&uri, location.line, location.character));
+
+    self.try_send_request(message, sender, |sender| Request::TextDocumentDocumentHighlight {
+      sender,
+      filepath,
+    })
+  }
+
+  fn document_highlights_response(
+    response: &Json,
+    filepath: PathBuf,
+  ) -> Result<GetDocumentHighlightsResponse, AnyhowError> {
+    let highlights = response
+      .get("result")
+      .unwrap_or(&Json::Null)
+      .to_value_from_value::<Option<Vec<DocumentHighlight>>>()?
+      .unwrap_or_default();
+
+    GetDocumentHighlightsResponse { filepath, highlights }.ok()
+  }
+
+  #[tracing::instrument(skip_all)]
+  fn get_workspace_symbols(
+    &mut self,
+    sender: OneshotSender<Result<GetWorkspaceSymbolsResponse, AnyhowError>>,
+    query: &str,
+    limit: Option<usize>,
+    kinds: Vec<usize>,
+  ) -> Result<(), AnyhowError> {
+    let message = Message::workspace_symbol_request(query);
+    let request = Request::WorkspaceSymbol { sender, limit, kinds };
+
+    self.send_request(message, request)
+  }
+
+  // NOTE: workspace/symbol has no parameters for limiting or filtering symbols
+  // by kind, so both are done on its response
+  fn workspace_symbols_response(
+    &self,
+    response: &Json,
+    limit: Option<usize>,
+    kinds: &[usize],
+  ) -> Result<GetWorkspaceSymbolsResponse, AnyhowError> {
+    response
+      .get("result")
+      .unwrap_or(&Json::Null)
+      .to_value_from_value::<Option<Vec<SymbolInformation>>>()?
+      .unwrap_or_default()
+      .into_iter()
+      .filter(|symbol_information| kinds.is_empty() || kinds.contains(&symbol_information.kind))
+      .take(limit.unwrap_or(usize::MAX))
+      .map(|symbol_information| {
+        WorkspaceSymbol {
+          location: self.file_range(&symbol_information.location)?,
+          name: symbol_information.name,
+          kind: symbol_information.kind,
+          container_name: symbol_information.container_name,
+        }
+        .ok()
+      })
+      .collect::<Result<Vec<_>, AnyhowError>>()?
+      .convert::<GetWorkspaceSymbolsResponse>()
+      .ok()
+  }
+
+  #[tracing::instrument(skip_all)]
+  fn get_semantic_tokens(
+    &mut self,
+    sender: OneshotSender<Result<GetSemanticTokensResponse, AnyhowError>>,
+    filepath: &Path,
+  ) -> Result<(), AnyhowError> {
+    let message = filepath
+      .to_uri()
+      .map(|uri| Message::text_document_semantic_tokens_full_request(&uri));
+
+    self.try_send_request(message, sender, Request::TextDocumentSemanticTokensFull)
+  }
+
+  #[tracing::instrument(skip_all)]
+  fn get_semantic_tokens_range(
+    &mut self,
+    sender: OneshotSender<Result<GetSemanticTokensResponse, AnyhowError>>,
+    range_location: &RangeLocation,
+  ) -> Result<(), AnyhowError> {
+    let message = range_location
+      .filepath
+      .to_uri()
+      .map(|uri| Message::text_document_semantic_tokens_range_request(&uri, &range_location.range()));
+
+    self.try_send_request(message, sender, Request::TextDocumentSemanticTokensRange)
+  }
+
+  fn semantic_tokens_response(&self, response: &Json) -> Result<GetSemanticTokensResponse, AnyhowError> {
+    let Some(semantic_tokens) = response
+      .get("result")
+      .unwrap_or(&Json::Null)
+      .to_value_from_value::<Option<SemanticTokens>>()?
+    else {
+      return GetSemanticTokensResponse::default().ok();
+    };
+    let tokens = self
+      .semantic_tokens_legend
+      .as_ref()
+      .context("lean server did not provide a semantic tokens legend")?
+      .decode(&semantic_tokens.data)?;
+
+    GetSemanticTokensResponse {
+      result_id: semantic_tokens.result_id,
+      data: semantic_tokens.data,
+      tokens,
+    }
+    .ok()
+  }
+
+  // NOTE: the legend is part of the capabilities that the lean server returns
+  // in its initialize response
+  fn store_semantic_tokens_legend(&mut self, response: &Json) -> Result<(), AnyhowError> {
+    self.semantic_tokens_legend = response
+      .pointer("/result/capabilities/semanticTokensProvider/legend")
+      .map(|legend| legend.to_value_from_value::<SemanticTokensLegend>())
+      .transpose()?;
+
+    ().ok()
+  }
+
+  fn open_filepath(&self, filepath: &Path) -> Option<PathBuf> {
+    self
+      .open_files
+      .keys()
+      .find(|open_filepath| {
+        open_filepath
+          .absolute()
+          .is_ok_and(|absolute_filepath| absolute_filepath == filepath)
+      })
+      .cloned()
+  }
+
+  async fn workspace_edit_plan(
+    open_files: &HashMap<PathBuf, Document>,
+    workspace_edit: &Json,
+  ) -> Result<WorkspaceEditPlan, AnyhowError> {
+    let open_documents = open_files
+      .iter()
+      .map(|(filepath, document)| (filepath.absolute()?.to_path_buf(), document).ok())
+      .collect::<Result<HashMap<_, _>, AnyhowError>>()?;
+
+    WorkspaceEditPlan::new(workspace_edit, &open_documents).await
+  }
+
+  // NOTE: open files are changed in place and only written to disk if
+  // requested, whereas files that are not open only exist on disk and so are
+  // always written
+  async fn apply_file_change(&mut self, file_change: &FileChange<'_>, write: bool) -> Result<(), AnyhowError> {
+    match (self.open_filepath(file_change.filepath), file_change.text) {
+      (Some(open_filepath), Some(text)) => {
+        self.change_file(&open_filepath, text.to_owned())?;
+
+        if write {
+          self.save_file(&open_filepath).await?;
+        }
+      }
+      (None, Some(text)) => {
+        if let Some(dirpath) = file_change.filepath.parent() {
+          tokio::fs::create_dir_all(dirpath)
+            .await
+            .context("unable to create directory")?;
+        }
+
+        tokio::fs::write(file_change.filepath, text)
+          .await
+          .context("unable to write file")?;
+      }
+      (_open_filepath, None) => tokio::fs::remove_file(file_change.filepath)
+        .await
+        .context("unable to delete file")?,
+    }
+
+    ().ok()
+  }
+
+  // NOTE: file changes made before a change fails, including the failed change
+  // itself, are reverted in reverse order; deleted directories cannot be
+  // restored, but edits that delete them are not text only and so are not
+  // transactional
+  #[tracing::instrument(skip_all)]
+  async fn apply_workspace_edit(
+    &mut self,
+    workspace_edit: &Json,
+    write: bool,
+  ) -> Result<ApplyWorkspaceEditResponse, AnyhowError> {
+    let workspace_edit_plan = Self::workspace_edit_plan(&self.open_files, workspace_edit).await?;
+    let diffs = workspace_edit_plan.diffs(&self.project_dirpath);
+    let mut file_changes = Vec::new();
+
+    for workspace_change in workspace_edit_plan.changes() {
+      let result = match workspace_change {
+        WorkspaceChange::File(file_change) => {
+          file_changes.push(file_change);
+
+          self.apply_file_change(&file_change, write).await
+        }
+        WorkspaceChange::DeletedDirectory(dirpath) => tokio::fs::remove_dir_all(dirpath)
+          .await
+          .context("unable to delete directory"),
+      };
+
+      if let Err(error) = result {
+        for file_change in file_changes.iter().rev() {
+          self
+            .apply_file_change(&file_change.reverted(), write)
+            .await
+            .context("unable to revert file change")
+            .log_if_error()
+            .unit();
+        }
+
+        return Err(error);
+      }
+    }
+
+    ApplyWorkspaceEditResponse::from(diffs).ok()
+  }
+
+  // NOTE: code actions without an edit are resolved by the lean server first
+  #[tracing::instrument(skip_all)]
+  async fn apply_code_action(
+    &mut self,
+    sender: OneshotSender<Result<ApplyWorkspaceEditResponse, AnyhowError>>,
+    action: &Json,
+    write: bool,
+  ) -> Result<(), AnyhowError> {
+    if let Some(workspace_edit) = action.get("edit") {
+      return self
+        .apply_workspace_edit(workspace_edit, write)
+        .await
+        .send_to_oneshot(sender);
+    }
+
+    let message = Message::code_action_resolve_request(action);
+    let request = Request::CodeActionResolve { sender, write };
+
+    self.send_request(message, request)
+  }
+
+  #[tracing::instrument(skip_all)]
+  fn send_raw_request(
+    &mut self,
+    sender: OneshotSender<Result<RawRequestResponse, AnyhowError>>,
+    method: &str,
+    params: Option<&Json>,
+  ) -> Result<(), AnyhowError> {
+    let message = Message::raw_request(method, params);
+    let request = Request::Raw(sender);
+
+    self.send_request(message, request)
+  }
+
+  #[tracing::instrument(skip_all)]
+  fn send_raw_notification(&self, method: &str, params: Option<&Json>) -> Result<(), AnyhowError> {
+    let notification = Message::raw_notification(method, params);
+
+    self.lean_server.send(notification)
+  }
+
+  fn connect_lean_rpc_session(&mut self, uri: String, rpc_calls: Vec<LeanRpcCall>) -> Result<(), AnyhowError> {
+    let message = Message::lean_rpc_connect_request(&uri);
+    let request = Request::LeanRpcConnect { uri, rpc_calls };
+
+    self.send_request(message, request)
+  }
+
+  fn process_lean_rpc_connect_response(
+    &mut self,
+    uri: String,
+    rpc_calls: Vec<LeanRpcCall>,
+    response: &Json,
+  ) -> Result<(), AnyhowError> {
+    let Some(session_id) = response.pointer("/result/sessionId").cloned() else {
+      let reason = "rpc connect response has no session id".to_owned();
+
+      Request::LeanRpcConnect { uri, rpc_calls }.fail(RequestFailure::LeanServerUnavailable { reason });
+
+      return ().ok();
+    };
+
+    self.lean_rpc_sessions.insert(uri, session_id);
+
+    for rpc_call in rpc_calls {
+      self.call_lean_rpc(rpc_call)?;
+    }
+
+    ().ok()
+  }
+
+  // NOTE: calls for documents without an rpc session wait for one to connect,
+  // joining a connection that is already in flight if there is one
+  fn call_lean_rpc(&mut self, rpc_call: LeanRpcCall) -> Result<(), AnyhowError> {
+    if let Some(session_id) = self.lean_rpc_sessions.get(&rpc_call.uri) {
+      let message = Message::lean_rpc_call_request(
+        &rpc_call.uri,
+        rpc_call.line,
+        rpc_call.character,
+        session_id,
+        &rpc_call.method,
+        &rpc_call.params,
+      );
+
+      return self.send_request(message, Request::LeanRpcCall(rpc_call));
+    }
+
+    let pending_rpc_calls = self
+      .requests
+      .values_mut()
+      .find_map(|pending_request| match &mut pending_request.request {
+        Request::LeanRpcConnect { uri, rpc_calls } if *uri == rpc_call.uri => rpc_calls.some(),
+        _request => None,
+      });
+
+    match pending_rpc_calls {
+      Some(rpc_calls) => rpc_calls.push(rpc_call).ok(),
+      None => self.connect_lean_rpc_session(rpc_call.uri.clone(), vec![rpc_call]),
+    }
+  }
+
+  #[tracing::instrument(skip_all)]
+  fn call_lean_rpc_method(
+    &mut self,
+    sender: LeanRpcSender,
+    location: &Location,
+    method: String,
+    params: Json,
+    keep_references: bool,
+  ) -> Result<(), AnyhowError> {
+    let uri = match location.filepath.to_uri() {
+      Ok(uri) => uri,
+      Err(error) => return sender.send_error(error).ok(),
+    };
+    let rpc_call = LeanRpcCall {
+      sender,
+      uri,
+      line: location.line,
+      character: location.character,
+      method,
+      params,
+      keep_references,
+      is_reconnected: false,
+    };
+
+    self.call_lean_rpc(rpc_call)
+  }
+
+  // NOTE: the lean server drops rpc sessions that it has not heard from in a
+  // while, e.g. after restarting the worker of a file, and asks for a new one
+  #[tracing::instrument(skip_all)]
+  fn reconnect_lean_rpc_call(&mut self, mut rpc_call: LeanRpcCall) -> Result<(), AnyhowError> {
+    tracing::info!(uri = rpc_call.uri, method = rpc_call.method, "reconnecting rpc session");
+
+    self.lean_rpc_sessions.remove(&rpc_call.uri);
+
+    rpc_call.is_reconnected = true;
+
+    self.call_lean_rpc(rpc_call)
+  }
+
+  // NOTE: references to objects held by the lean server are encoded as objects
+  // with just a `p` field; this includes lazy trace children, which can thus
+  // only be expanded later if references are kept
+  fn lean_rpc_references(json: &Json, references: &mut Vec<Json>) {
+    match json {
+      Json::Object(object) if object.len() == 1 && object.contains_key("p") => references.push(json.clone()),
+      Json::Object(object) => {
+        for value in object.values() {
+          Self::lean_rpc_references(value, references);
+        }
+      }
+      Json::Array(values) => {
+        for value in values {
+          Self::lean_rpc_references(value, references);
+        }
+      }
+      _json => (),
+    }
+  }
+
+  fn process_lean_rpc_call_response(&self, rpc_call: LeanRpcCall, response: &Json) -> Result<(), AnyhowError> {
+    let result = response.get("result").cloned().unwrap_or_default();
+
+    if !rpc_call.keep_references {
+      let mut references = Vec::new();
+
+      Self::lean_rpc_references(&result, &mut references);
+
+      self
+        .release_lean_rpc_references(&rpc_call.uri, &references)
+        .log_if_error()
+        .unit();
+    }
+
+    rpc_call.sender.send_result(result)
+  }
+
+  fn release_lean_rpc_references(&self, uri: &str, refs: &[Json]) -> Result<(), AnyhowError> {
+    if refs.is_empty() {
+      return ().ok();
+    }
+
+    let session_id = self
+      .lean_rpc_sessions
+      .get(uri)
+      .with_context(|| format!("no rpc session for {uri}"))?;
+    let notification = Message::lean_rpc_release_notification(uri, session_id, refs);
+
+    self.lean_server.send(notification)
+  }
+
+  fn keep_lean_rpc_sessions_alive(&self) {
+    for (uri, session_id) in &self.lean_rpc_sessions {
+      let notification = Message::lean_rpc_keep_alive_notification(uri, session_id);
+
+      self.lean_server.send(notification).log_if_error().unit();
+    }
+  }
+
+  #[tracing::instrument(skip_all)]
+  fn get_plain_goals(
+    &mut self,
+    sender: OneshotSender<Result<GetPlainGoalsResponse, AnyhowError>>,
+    location: &Location,
+  ) -> Result<(), AnyhowError> {
+    let request_message = location
+      .filepath
+      .to_uri()
+      .map(|uri| Message::lean_rpc_get_plain_goals_request(&uri, location.line, location.character));
+
+    self.try_send_request(request_message, sender, Request::GetPlainGoals)
   }
 
-  fn initialize(&mut self, sender: OneshotSender<()>) -> Result<(), AnyhowError> {
-    let request_message = self.lean_server.initialize_request()?;
-    let request = Request::Initialize(sender);
+  #[tracing::instrument(skip_all)]
+  fn get_plain_term_goal(
+    &mut self,
+    sender: OneshotSender<Result<GetPlainTermGoalResponse, AnyhowError>>,
+    location: &Location,
+  ) -> Result<(), AnyhowError> {
+    let request_message = location
+      .filepath
+      .to_uri()
+      .map(|uri| Message::lean_rpc_get_plain_term_goal_request(&uri, location.line, location.character));
 
-    self.send_request(request_message, request)
+    self.try_send_request(request_message, sender, Request::GetPlainTermGoal)
   }
 
   #[tracing::instrument(skip_all)]
-  async fn open_file(&mut self, filepath: PathBuf) -> Result<(), AnyhowError> {
-    if self.open_file_versions.contains_key(&filepath) {
-      anyhow::bail!("file {} is already open", filepath.display());
-    }
-
-    let uri = filepath.to_uri()?;
-    let text = filepath
-      .open_async()
-      .await?
-      .buf_reader_async()
-      .read_string_async()
-      .await?;
-    let text_document_did_open_notification = Message::text_document_did_open_notification(&text, &uri);
-    let text_document_document_symbol_request = Message::text_document_document_symbol_request(&uri);
-    let text_document_document_code_action_request = Message::text_document_document_code_action_request(&uri);
-    let text_document_folding_range_request = Message::text_document_folding_range_request(&uri);
-    let lean_rpc_connect_request = Message::lean_rpc_connect_request(&uri);
+  fn get_interactive_goals(
+    &mut self,
+    sender: OneshotSender<Result<GetInteractiveGoalsResponse, AnyhowError>>,
+    location: &Location,
+    keep_references: bool,
+  ) -> Result<(), AnyhowError> {
+    let uri = match location.filepath.to_uri() {
+      Ok(uri) => uri,
+      Err(error) => return Request::send_error(sender, error).ok(),
+    };
+    let params = crate::messages::lean_rpc::get_plain_goals_params(&uri, location.line, location.character);
+    let sender = LeanRpcSender::InteractiveGoals(sender);
+    let method = Self::LEAN_RPC_GET_INTERACTIVE_GOALS_METHOD.to_owned();
 
-    self.lean_server.send(text_document_did_open_notification)?;
-    self.send_request(
-      text_document_document_symbol_request,
-      Request::TextDocumentDocumentSymbol,
-    )?;
-    self.send_request(
-      text_document_document_code_action_request,
-      Request::TextDocumentDocumentCodeAction,
-    )?;
-    self.send_request(text_document_folding_range_request, Request::TextDocumentFoldingRange)?;
-    self.send_request(lean_rpc_connect_request, Request::LeanRpcConnect)?;
+    self.call_lean_rpc_method(sender, location, method, params, keep_references)
+  }
 
-    self.open_file_versions.insert(filepath, INITIAL_TEXT_DOCUMENT_VERSION);
+  #[tracing::instrument(skip_all)]
+  fn get_interactive_diagnostics(
+    &mut self,
+    sender: OneshotSender<Result<GetInteractiveDiagnosticsResponse, AnyhowError>>,
+    filepath: PathBuf,
+    start_line: Option<usize>,
+    end_line: Option<usize>,
+    keep_references: bool,
+  ) -> Result<(), AnyhowError> {
+    let location = Location::new(filepath, start_line.unwrap_or_default(), 0);
+    let params = crate::messages::lean_rpc::get_interactive_diagnostics_params(start_line, end_line);
+    let sender = LeanRpcSender::InteractiveDiagnostics(sender);
+    let method = Self::LEAN_RPC_GET_INTERACTIVE_DIAGNOSTICS_METHOD.to_owned();
 
-    ().ok()
+    self.call_lean_rpc_method(sender, &location, method, params, keep_references)
   }
 
   #[tracing::instrument(skip_all)]
-  fn change_file(&mut self, filepath: &Path, text: &str) -> Result<(), AnyhowError> {
-    let version = self
-      .open_file_versions
-      .get_mut(filepath)
-      .context_path("file is not open", filepath)?;
-    let new_version = *version + 1;
-
-    let uri = filepath.to_uri()?;
-    let text_document_did_change_notification = Message::text_document_did_change_notification(text, &uri, new_version);
+  fn expand_trace_children(
+    &mut self,
+    sender: OneshotSender<Result<ExpandTraceChildrenResponse, AnyhowError>>,
+    filepath: PathBuf,
+    children: Json,
+    keep_references: bool,
+  ) -> Result<(), AnyhowError> {
+    let location = Location::new(filepath, 0, 0);
+    let sender = LeanRpcSender::TraceChildren(sender);
+    let method = Self::LEAN_RPC_LAZY_TRACE_CHILDREN_METHOD.to_owned();
 
-    self.lean_server.send(text_document_did_change_notification)?;
+    self.call_lean_rpc_method(sender, &location, method, children, keep_references)
+  }
 
-    // only increment the version if the request was successfully sent
-    *version += 1;
+  fn get_diagnostics(&self, filepath: &Path, filter: &DiagnosticFilter) -> Result<GetDiagnosticsResponse, AnyhowError> {
+    let uri = filepath.to_uri()?;
 
-    ().ok()
+    self.file_reports.file_diagnostics(&uri, filter).ok()
   }
 
-  #[tracing::instrument(skip_all)]
-  fn close_file(&mut self, filepath: &Path) -> Result<(), AnyhowError> {
-    if !self.open_file_versions.contains_key(filepath) {
-      anyhow::bail!("file {} is not open", filepath.display());
-    }
-
+  fn file_waiter_uri_version(&self, filepath: &Path, version: Option<usize>) -> Result<(String, usize), AnyhowError> {
+    let document = self
+      .open_files
+      .get(filepath)
+      .context_path("file is not open", filepath)?;
     let uri = filepath.to_uri()?;
-    let text_document_did_close_notification = Message::text_document_did_close_notification(&uri);
+    let version = version.unwrap_or(document.version);
 
-    self.lean_server.send(text_document_did_close_notification)?;
+    (uri, version).ok()
+  }
 
-    self.open_file_versions.remove(filepath);
+  fn wait_for_file(
+    &mut self,
+    sender: OneshotSender<Result<GetDiagnosticsResponse, AnyhowError>>,
+    filepath: &Path,
+    version: Option<usize>,
+    timeout_ms: Option<u64>,
+    filter: DiagnosticFilter,
+  ) {
+    let (uri, version) = match self.file_waiter_uri_version(filepath, version) {
+      Ok(uri_version) => uri_version,
+      Err(error) => return Request::send_error(sender, error),
+    };
+    let start_instant = Instant::now();
+    let timeout = timeout_ms.map_or(Self::DEFAULT_WAIT_FOR_FILE_TIMEOUT, Duration::from_millis);
+    let file_waiter = FileWaiter {
+      uri,
+      version,
+      filter,
+      start_instant,
+      timeout,
+      sender,
+    };
 
-    ().ok()
+    self.file_reports.wait(file_waiter);
   }
 
-  #[tracing::instrument(skip_all)]
-  fn hover_file(&mut self, sender: OneshotSender<HoverFileResponse>, location: &Location) -> Result<(), AnyhowError> {
-    let uri = location.filepath.to_uri()?;
-    let message = Message::text_document_hover_request(&uri, location.line, location.character);
-    let request = Request::Hover(sender);
+  fn reply_to_server_request(
+    &mut self,
+    id: &Json,
+    result: Option<Json>,
+    error: Option<Json>,
+  ) -> Result<(), AnyhowError> {
+    let id = id.to_value_from_value::<Id>()?;
 
-    self.send_request(message, request)?;
+    if self.pending_server_requests.remove(&id).is_none() {
+      anyhow::bail!("no pending server request with id {id}");
+    }
 
-    ().ok()
+    let response = match error {
+      Some(error) => Message::error_response(&id, &error),
+      None => Message::response(&id, &result.unwrap_or_default()),
+    };
+
+    self.lean_server.send(response)
   }
 
-  #[tracing::instrument(skip_all)]
-  fn get_plain_goals(
-    &mut self,
-    sender: OneshotSender<GetPlainGoalsResponse>,
-    location: &Location,
-  ) -> Result<(), AnyhowError> {
-    let uri = location.filepath.to_uri()?;
-    let request_message = Message::lean_rpc_get_plain_goals_request(&uri, location.line, location.character);
-    let request = Request::GetPlainGoals(sender);
+  fn fail_server_request(&self, id: &Id, code: i64, message: &str) -> Result<(), AnyhowError> {
+    let error = Message::response_error(code, message);
+    let response = Message::error_response(id, &error);
 
-    self.send_request(request_message, request)
+    self.lean_server.send(response)
   }
 
   fn get_status(&self) -> SessionStatus {
@@ -220,6 +2336,7 @@ impl SessionRunner {
   }
 
   fn kill(&mut self) {
+    self.fail_requests(&RequestFailure::SessionKilled);
     self.lean_server.kill();
     self.kill_event_sender.set();
   }
@@ -228,88 +2345,881 @@ impl SessionRunner {
   async fn process_command(&mut self, session_command: SessionCommand) -> Result<(), AnyhowError> {
     match session_command {
       SessionCommand::Initialize { sender } => self.initialize(sender),
-      SessionCommand::OpenFile { sender, filepath } => self.open_file(filepath).await.send_to_oneshot(sender),
+      SessionCommand::OpenFile { sender, filepath, text } => {
+        self.open_file(filepath, text).await.send_to_oneshot(sender)
+      }
       SessionCommand::ChangeFile { sender, filepath, text } => {
-        self.change_file(&filepath, &text).send_to_oneshot(sender)
+        self.change_file(&filepath, text).send_to_oneshot(sender)
       }
+      SessionCommand::EditFile {
+        sender,
+        filepath,
+        edits,
+      } => self.edit_file(&filepath, &edits).send_to_oneshot(sender),
       SessionCommand::HoverFile { sender, location } => self.hover_file(sender, &location),
+      SessionCommand::GetFile { sender, filepath } => self.get_file(&filepath).send_to_oneshot(sender),
+      SessionCommand::SaveFile { sender, filepath } => self.save_file(&filepath).await.send_to_oneshot(sender),
       SessionCommand::CloseFile { sender, filepath } => self.close_file(&filepath).send_to_oneshot(sender),
+      SessionCommand::GetDefinition { sender, location } => self.get_locations(
+        sender,
+        &location,
+        Message::text_document_definition_request,
+        Request::Definition,
+      ),
+      SessionCommand::GetDeclaration { sender, location } => self.get_locations(
+        sender,
+        &location,
+        Message::text_document_declaration_request,
+        Request::Declaration,
+      ),
+      SessionCommand::GetTypeDefinition { sender, location } => self.get_locations(
+        sender,
+        &location,
+        Message::text_document_type_definition_request,
+        Request::TypeDefinition,
+      ),
+      SessionCommand::GetReferences {
+        sender,
+        location,
+        include_declaration,
+      } => self.get_references(sender, &location, include_declaration),
+      SessionCommand::PrepareRename { sender, location } => self.prepare_rename(sender, &location),
+      SessionCommand::Rename {
+        sender,
+        location,
+        new_name,
+      } => self.rename(sender, &location, &new_name),
+      SessionCommand::GetCompletions {
+        sender,
+        location,
+        trigger_kind,
+        trigger_character,
+      } => self.get_completions(sender, &location, trigger_kind, trigger_character.as_deref()),
+      SessionCommand::ResolveCompletionItem { sender, item } => self.resolve_completion_item(sender, &item),
+      SessionCommand::GetDocumentSymbols { sender, filepath } => self.get_document_symbols(sender, &filepath),
+      SessionCommand::GetCodeActions { sender, range_location } => self.get_code_actions(sender, &range_location),
+      SessionCommand::GetFoldingRanges { sender, filepath } => self.get_folding_ranges(sender, &filepath),
+      SessionCommand::PrepareCallHierarchy { sender, location } => self.prepare_call_hierarchy(sender, &location),
+      SessionCommand::GetCallHierarchyCalls { sender, item, outgoing } => {
+        self.get_call_hierarchy_calls(sender, &item, outgoing)
+      }
+      SessionCommand::GetInlayHints { sender, range_location } => self.get_inlay_hints(sender, &range_location),
+      SessionCommand::GetDocumentHighlights { sender, location } => self.get_document_highlights(sender, &location),
+      SessionCommand::GetWorkspaceSymbols {
+        sender,
+        query,
+        limit,
+        kinds,
+      } => self.get_workspace_symbols(sender, &query, limit, kinds),
+      SessionCommand::GetSemanticTokens { sender, filepath } => self.get_semantic_tokens(sender, &filepath),
+      SessionCommand::GetSemanticTokensRange { sender, range_location } => {
+        self.get_semantic_tokens_range(sender, &range_location)
+      }
+      SessionCommand::ApplyWorkspaceEdit {
+        sender,
+        workspace_edit,
+        write,
+      } => self
+        .apply_workspace_edit(&workspace_edit, write)
+        .await
+        .send_to_oneshot(sender),
+      SessionCommand::ApplyCodeAction { sender, action, write } => self.apply_code_action(sender, &action, write).await,
       SessionCommand::GetPlainGoals { sender, location } => self.get_plain_goals(sender, &location),
+      SessionCommand::GetPlainTermGoal { sender, location } => self.get_plain_term_goal(sender, &location),
+      SessionCommand::GetInteractiveGoals {
+        sender,
+        location,
+        keep_references,
+      } => self.get_interactive_goals(sender, &location, keep_references),
+      SessionCommand::GetDiagnostics {
+        sender,
+        filepath,
+        filter,
+      } => self.get_diagnostics(&filepath, &filter).send_to_oneshot(sender),
+      SessionCommand::GetInteractiveDiagnostics {
+        sender,
+        filepath,
+        start_line,
+        end_line,
+        keep_references,
+      } => self.get_interactive_diagnostics(sender, filepath, start_line, end_line, keep_references),
+      SessionCommand::ExpandTraceChildren {
+        sender,
+        filepath,
+        children,
+        keep_references,
+      } => self.expand_trace_children(sender, filepath, children, keep_references),
+      SessionCommand::WaitForFile {
+        sender,
+        filepath,
+        version,
+        timeout_ms,
+        filter,
+      } => self.wait_for_file(sender, &filepath, version, timeout_ms, filter).ok(),
+      SessionCommand::CallLeanRpc {
+        sender,
+        location,
+        method,
+        params,
+        keep_references,
+      } => self.call_lean_rpc_method(
+        LeanRpcSender::Call(sender),
+        &location,
+        method,
+        params.unwrap_or_default(),
+        keep_references,
+      ),
+      SessionCommand::ReleaseLeanRpcReferences { sender, filepath, refs } => filepath
+        .to_uri()
+        .and_then(|uri| self.release_lean_rpc_references(&uri, &refs))
+        .send_to_oneshot(sender),
+      SessionCommand::ReplyToServerRequest {
+        sender,
+        id,
+        result,
+        error,
+      } => self.reply_to_server_request(&id, result, error).send_to_oneshot(sender),
+      SessionCommand::SendRawRequest { sender, method, params } => {
+        self.send_raw_request(sender, &method, params.as_ref())
+      }
+      SessionCommand::SendRawNotification { sender, method, params } => self
+        .send_raw_notification(&method, params.as_ref())
+        .send_to_oneshot(sender),
       SessionCommand::GetStatus { sender } => self.get_status().send_to_oneshot(sender),
       SessionCommand::Kill { sender } => self.kill().send_to_oneshot(sender),
     }
   }
 
   #[tracing::instrument(skip_all, err)]
-  fn process_response(&mut self, request: Request, response: &Json) -> Result<(), AnyhowError> {
+  async fn process_response(&mut self, request: Request, response: &Json) -> Result<(), AnyhowError> {
     tracing::info!(received_response = response.to_value(), %request, "received response for request");
 
+    // NOTE: raw requests get error responses back as is
+    if let Some(error) = response.get("error")
+      && !matches!(request, Request::Raw(_))
+    {
+      let code = error.get("code").and_then(Json::as_i64).unwrap_or_default();
+      let message = error
+        .get("message")
+        .and_then(Json::as_str)
+        .unwrap_or_default()
+        .to_owned();
+
+      let request = match request {
+        Request::LeanRpcCall(rpc_call)
+          if code == Self::LEAN_RPC_NEEDS_RECONNECT_ERROR_CODE && !rpc_call.is_reconnected =>
+        {
+          return self.reconnect_lean_rpc_call(rpc_call);
+        }
+        request => request,
+      };
+
+      request.fail(RequestFailure::ResponseError { code, message });
+
+      return ().ok();
+    }
+
     match request {
       Request::Initialize(sender) => {
         let notification = Message::initialized_notification();
 
-        ().send_to_oneshot(sender)?;
+        self
+          .store_semantic_tokens_legend(response)
+          .context("invalid semantic tokens legend")
+          .log_if_error()
+          .unit();
+        ().ok().send_to_oneshot(sender)?;
+        self.lean_server.send(notification)?;
+      }
+      Request::Reinitialize => {
+        let notification = Message::initialized_notification();
+
+        self
+          .store_semantic_tokens_legend(response)
+          .context("invalid semantic tokens legend")
+          .log_if_error()
+          .unit();
         self.lean_server.send(notification)?;
+        self.reopen_files()?;
+        self.send_lean_server_lifecycle_notification(&LeanServerLifecycleEvent::Restarted, None);
       }
       Request::GetPlainGoals(sender) => response
-        .to_value_from_value::<GetPlainGoalsResponse>()?
+        .to_value_from_value::<GetPlainGoalsResponse>()
+        .context("invalid plain goals response")
+        .send_to_oneshot(sender)?,
+      Request::GetPlainTermGoal(sender) => response
+        .to_value_from_value::<GetPlainTermGoalResponse>()
+        .context("invalid plain term goal response")
         .send_to_oneshot(sender)?,
       Request::Hover(sender) => response
-        .to_value_from_value::<HoverFileResponse>()?
+        .to_value_from_value::<HoverFileResponse>()
+        .context("invalid hover response")
+        .send_to_oneshot(sender)?,
+      Request::Definition(sender)
+      | Request::Declaration(sender)
+      | Request::TypeDefinition(sender)
+      | Request::References(sender) => self
+        .locations_response(response)
+        .context("invalid locations response")
+        .send_to_oneshot(sender)?,
+      Request::PrepareRename(sender) => Self::prepare_rename_response(response)
+        .context("invalid prepare rename response")
+        .send_to_oneshot(sender)?,
+      Request::Rename(sender) => self
+        .rename_response(response)
+        .await
+        .context("invalid rename response")
+        .send_to_oneshot(sender)?,
+      Request::Completion(sender) => Self::completions_response(response)
+        .context("invalid completion response")
+        .send_to_oneshot(sender)?,
+      Request::CompletionItemResolve(sender) => response
+        .get("result")
+        .cloned()
+        .unwrap_or_default()
+        .convert::<ResolveCompletionItemResponse>()
+        .ok()
+        .send_to_oneshot(sender)?,
+      Request::Raw(sender) => RawRequestResponse::from(response.clone())
+        .ok()
+        .send_to_oneshot(sender)?,
+      Request::TextDocumentDocumentSymbol(sender) => response
+        .get("result")
+        .cloned()
+        .unwrap_or_default()
+        .to_value_from_value::<Option<Vec<DocumentSymbol>>>()
+        .map(|symbols| GetDocumentSymbolsResponse::from(symbols.unwrap_or_default()))
+        .context("invalid document symbol response")
         .send_to_oneshot(sender)?,
+      Request::TextDocumentDocumentCodeAction(sender) => response
+        .get("result")
+        .cloned()
+        .unwrap_or_default()
+        .to_value_from_value::<Option<Vec<Json>>>()
+        .map(|actions| GetCodeActionsResponse::from(actions.unwrap_or_default()))
+        .context("invalid code action response")
+        .send_to_oneshot(sender)?,
+      Request::TextDocumentFoldingRange(sender) => response
+        .get("result")
+        .cloned()
+        .unwrap_or_default()
+        .to_value_from_value::<Option<Vec<FoldingRange>>>()
+        .map(|ranges| GetFoldingRangesResponse::from(ranges.unwrap_or_default()))
+        .context("invalid folding range response")
+        .send_to_oneshot(sender)?,
+      Request::TextDocumentPrepareCallHierarchy(sender) => self
+        .prepare_call_hierarchy_response(response)
+        .context("invalid prepare call hierarchy response")
+        .send_to_oneshot(sender)?,
+      Request::CallHierarchyIncomingCalls(sender) | Request::CallHierarchyOutgoingCalls(sender) => self
+        .call_hierarchy_calls_response(response)
+        .context("invalid call hierarchy calls response")
+        .send_to_oneshot(sender)?,
+      Request::TextDocumentInlayHint { sender, filepath } => Self::inlay_hints_response(response, filepath)
+        .context("invalid inlay hint response")
+        .send_to_oneshot(sender)?,
+      Request::TextDocumentDocumentHighlight { sender, filepath } => {
+        Self::document_highlights_response(response, filepath)
+          .context("invalid document highlight response")
+          .send_to_oneshot(sender)?
+      }
+      Request::WorkspaceSymbol { sender, limit, kinds } => self
+        .workspace_symbols_response(response, limit, &kinds)
+        .context("invalid workspace symbol response")
+        .send_to_oneshot(sender)?,
+      Request::TextDocumentSemanticTokensFull(sender) | Request::TextDocumentSemanticTokensRange(sender) => self
+        .semantic_tokens_response(response)
+        .context("invalid semantic tokens response")
+        .send_to_oneshot(sender)?,
+      Request::CodeActionResolve { sender, write } => {
+        let apply_workspace_edit_res = match response
+          .get("result")
+          .and_then(|action| action.get("edit"))
+          .context("resolved code action has no edit")
+        {
+          Ok(workspace_edit) => self.apply_workspace_edit(workspace_edit, write).await,
+          Err(error) => Err(error),
+        };
+
+        apply_workspace_edit_res.send_to_oneshot(sender)?;
+      }
+      Request::LeanRpcConnect { uri, rpc_calls } => {
+        self.process_lean_rpc_connect_response(uri, rpc_calls, response)?;
+      }
+      Request::LeanRpcCall(rpc_call) => self.process_lean_rpc_call_response(rpc_call, response)?,
+    }
+
+    ().ok()
+  }
+
+  fn register_capabilities(&mut self, params: &Json) {
+    let registrations = params
+      .get("registrations")
+      .and_then(Json::as_array)
+      .into_iter()
+      .flatten();
+
+    for registration in registrations {
+      let Some(registration_id) = registration.get("id").and_then(Json::as_str) else { continue };
+
+      self
+        .capability_registrations
+        .insert(registration_id.to_owned(), registration.clone());
+    }
+  }
+
+  fn unregister_capabilities(&mut self, params: &Json) {
+    // NOTE: the misspelling is part of the protocol
+    let unregistrations = params
+      .get("unregisterations")
+      .and_then(Json::as_array)
+      .into_iter()
+      .flatten();
+
+    for unregistration in unregistrations {
+      let Some(registration_id) = unregistration.get("id").and_then(Json::as_str) else { continue };
+
+      self.capability_registrations.remove(registration_id);
+    }
+  }
+
+  fn update_file_system_watchers(&mut self) -> Result<(), AnyhowError> {
+    self.file_system_watchers = FileSystemWatcher::from_registrations(self.capability_registrations.values());
+
+    self.update_file_watcher()
+  }
+
+  // NOTE: the file watcher is only kept around while something needs it, as
+  // watching the project recursively can be expensive
+  fn update_file_watcher(&mut self) -> Result<(), AnyhowError> {
+    let is_needed = !self.file_system_watchers.is_empty() || (self.auto_reload && !self.open_files.is_empty());
+
+    if !is_needed {
+      self.file_watcher = None;
+    } else if self.file_watcher.is_none() {
+      self.file_watcher = FileWatcher::new(&self.project_dirpath)?.some();
+    }
+
+    ().ok()
+  }
+
+  async fn reload_file(&mut self, filepath: &Path) -> Result<(), AnyhowError> {
+    let Some(open_filepath) = self.open_filepath(filepath) else { return ().ok() };
+    let text = tokio::fs::read_to_string(filepath).await?;
+
+    // NOTE: skips writes that leave the text unchanged, such as saving the
+    // open buffer
+    if self
+      .open_files
+      .get(&open_filepath)
+      .is_some_and(|document| document.text == text)
+    {
+      return ().ok();
+    }
+
+    tracing::info!(filepath = %open_filepath.display(), "reloading file changed on disk");
+
+    self.change_file(&open_filepath, text)
+  }
+
+  #[tracing::instrument(skip_all)]
+  async fn process_file_event(&mut self, event_res: Result<NotifyEvent, AnyhowError>) -> Result<(), AnyhowError> {
+    let event = event_res?;
+    let Some(file_change_type) = FileChangeType::new(&event.kind) else { return ().ok() };
+    let mut changes = Vec::new();
+
+    for filepath in &event.paths {
+      let is_watched = self
+        .file_system_watchers
+        .iter()
+        .any(|file_system_watcher| file_system_watcher.matches(filepath, file_change_type));
+
+      if is_watched {
+        changes.push(workspace::file_event(&filepath.to_uri()?, file_change_type.lsp_type()));
+      }
+    }
+
+    if !changes.is_empty() {
+      let workspace_did_change_watched_files_notification =
+        Message::workspace_did_change_watched_files_notification(&changes);
+
+      self.lean_server.send(workspace_did_change_watched_files_notification)?;
+    }
 
-      // explicitly name ignored requests so new variants cause a compile error.
-      Request::TextDocumentDocumentSymbol
-      | Request::TextDocumentDocumentCodeAction
-      | Request::TextDocumentFoldingRange
-      | Request::LeanRpcConnect => (),
+    if self.auto_reload && file_change_type != FileChangeType::Deleted {
+      for filepath in &event.paths {
+        self.reload_file(filepath).await?;
+      }
     }
 
     ().ok()
   }
 
-  #[allow(clippy::unused_self)]
+  // NOTE: returns `None` for requests that should be forwarded to subscribers
+  // to reply to
+  async fn server_request_result(&mut self, method: &str, params: &Json) -> Option<Json> {
+    // NOTE: capability registrations are tracked even when a reply is
+    // configured for them, as the file watcher depends on them
+    match method {
+      "client/registerCapability" => self.register_capabilities(params),
+      "client/unregisterCapability" => self.unregister_capabilities(params),
+      _ => return self.server_request_reply(method, params).await,
+    }
+
+    self.update_file_system_watchers().log_if_error().unit();
+
+    self.server_request_reply(method, params).await
+  }
+
+  async fn server_request_reply(&mut self, method: &str, params: &Json) -> Option<Json> {
+    if let Some(result) = self.server_request_replies.get(method) {
+      return result.clone().some();
+    }
+
+    let result = match method {
+      "workspace/configuration" => workspace::configuration_result(params),
+      "workspace/applyEdit" => {
+        let workspace_edit = params.get("edit").unwrap_or(&Json::Null);
+
+        match self.apply_workspace_edit(workspace_edit, false).await {
+          Ok(_apply_workspace_edit_response) => workspace::apply_edit_result(true, None),
+          Err(error) => workspace::apply_edit_result(false, error.to_string().as_str().some()),
+        }
+      }
+      "window/showDocument" => window::show_document_result(false),
+      "client/registerCapability"
+      | "client/unregisterCapability"
+      | "window/workDoneProgress/create"
+      | "window/showMessageRequest"
+      | "workspace/codeLens/refresh"
+      | "workspace/diagnostic/refresh"
+      | "workspace/inlayHint/refresh"
+      | "workspace/inlineValue/refresh"
+      | "workspace/semanticTokens/refresh" => Json::Null,
+      _ => return None,
+    };
+
+    result.some()
+  }
+
   #[tracing::instrument(skip_all)]
-  fn process_request(&self, request: &Json) {
+  async fn process_request(&mut self, id: Id, request: Json) -> Result<(), AnyhowError> {
     tracing::info!(received_request = request.to_value(), "received request");
+
+    let method = request.get("method").and_then(Json::as_str).unwrap_or_default();
+    let params = request.get("params").unwrap_or(&Json::Null);
+
+    if let Some(result) = self.server_request_result(method, params).await {
+      let response = Message::response(&id, &result);
+
+      return self.lean_server.send(response);
+    }
+
+    tracing::info!(%id, method, "forwarding server request to subscribers");
+
+    let method = method.to_owned();
+
+    // NOTE: a request that no subscriber receives would never be replied to
+    if self.notifications.send(request).is_err() {
+      let message = format!("no subscribers to reply to {method}");
+
+      tracing::warn!(%id, method, "no subscribers to forward server request to");
+
+      return self.fail_server_request(&id, Self::METHOD_NOT_FOUND_ERROR_CODE, &message);
+    }
+
+    let received_instant = Instant::now();
+    let pending_server_request = PendingServerRequest {
+      method,
+      received_instant,
+    };
+
+    self.pending_server_requests.insert(id, pending_server_request);
+
+    ().ok()
+  }
+
+  fn store_diagnostics(&mut self, params: &Json) -> Result<(), AnyhowError> {
+    let params = params.to_value_from_value::<PublishDiagnosticsParams>()?;
+    let file_diagnostics = FileDiagnostics {
+      version: params.version,
+      diagnostics: params.diagnostics,
+    };
+
+    self.file_reports.store_diagnostics(params.uri, file_diagnostics);
+
+    ().ok()
+  }
+
+  fn update_file_progress(&mut self, params: &Json) -> Result<(), AnyhowError> {
+    let params = params.to_value_from_value::<FileProgressParams>()?;
+    let file_progress = FileProgress {
+      version: params.text_document.version,
+      is_processing: !params.processing.is_empty(),
+    };
+
+    self
+      .file_reports
+      .update_progress(params.text_document.uri, file_progress);
+
+    ().ok()
   }
 
   #[tracing::instrument(skip_all)]
   fn process_notification(&mut self, notification: Json) {
     tracing::info!(received_notification = notification.to_value(), "received notification");
 
+    let method = notification.get("method").and_then(Json::as_str);
+    let params = notification.get("params").unwrap_or(&Json::Null);
+
+    match method {
+      Some("textDocument/publishDiagnostics") => self
+        .store_diagnostics(params)
+        .context("invalid diagnostics notification")
+        .log_if_error()
+        .unit(),
+      Some("$/lean/fileProgress") => self
+        .update_file_progress(params)
+        .context("invalid file progress notification")
+        .log_if_error()
+        .unit(),
+      _ => (),
+    }
+
     self.notifications.send(notification).log_if_error().unit()
   }
 
   #[tracing::instrument(skip_all, err)]
-  fn process_message(&mut self, message: Json) -> Result<(), AnyhowError> {
+  async fn process_message(&mut self, message: Json) -> Result<(), AnyhowError> {
     tracing::info!(received_message = message.to_value(), "received message");
 
     let Some(id) = message.get("id") else { return self.process_notification(message).ok() };
     let id = id.to_value_from_value::<Id>()?;
 
-    if let Some(request) = self.requests.remove(&id) {
-      self.process_response(request, &message)
+    if let Some(pending_request) = self.requests.remove(&id) {
+      self.process_response(pending_request.request, &message).await
+    } else if message.get("method").is_some() {
+      self.process_request(id, message).await
     } else {
-      self.process_request(&message).ok()
+      tracing::info!(%id, "received response for cancelled request");
+
+      ().ok()
     }
   }
 
   #[tracing::instrument(skip_all)]
-  async fn result(mut self) -> Result<(), AnyhowError> {
+  async fn result(&mut self) -> Result<(), AnyhowError> {
     loop {
+      let lean_server_restart_instant = self.lean_server_restart_instant.unwrap_or_else(Instant::now);
+
+      // NOTE: commands are not fatal to the session as they can fail while the
+      // lean server is being restarted; the select is biased towards the kill
+      // event so that a killed session never goes on to restart the lean server
       tokio::select! {
-        session_command_res = self.commands.next_item_async() => self.process_command(session_command_res?).await?,
-        json_message_res = self.lean_server.recv::<Json>() => self.process_message(json_message_res?)?,
+        biased;
+
         () = self.kill_event_receiver.wait() => return ().ok(),
+        session_command_res = self.commands.next_item_async() => self.process_command(session_command_res?).await.context("error processing command").log_if_error().unit(),
+        json_message_res = self.lean_server.recv::<Json>(), if self.lean_server_restart_instant.is_none() => match json_message_res? {
+          Some(json_message) => self.process_message(json_message).await?,
+          None => self.process_lean_server_exit().await?,
+        },
+        () = tokio::time::sleep_until(lean_server_restart_instant), if self.lean_server_restart_instant.is_some() => self.restart_lean_server()?,
+        _instant = self.request_sweep_interval.tick() => self.sweep_requests(),
+        _instant = self.lean_rpc_keep_alive_interval.tick(), if self.lean_server_restart_instant.is_none() => self.keep_lean_rpc_sessions_alive(),
+        file_event_res = FileWatcher::next_event(&mut self.file_watcher) => self.process_file_event(file_event_res).await.context("error processing file event").log_if_error().unit(),
       }
     }
   }
 
-  pub async fn run(self) -> SessionResult {
+  pub async fn run(mut self) -> SessionResult {
     let id = self.id;
     let result = self.result().await;
 
+    self.fail_requests(&RequestFailure::SessionEnded);
+
     SessionResult { id, result }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use std::{path::Path, time::Duration};
+
+  use anyhow::Error as AnyhowError;
+  use mkutils::Utils;
+  use tokio::{
+    sync::oneshot::{Receiver as OneshotReceiver, error::TryRecvError},
+    time::Instant,
+  };
+
+  use super::{FileProgress, FileReports, FileWaiter, PendingRequest, Request, SemanticTokensLegend, SessionRunner};
+  use crate::{
+    server::responses::GetDiagnosticsResponse,
+    types::{Diagnostic, DiagnosticFilter, FileDiagnostics, Position, Range, SemanticToken},
+  };
+
+  const URI: &str = "file:///project/Main.lean";
+
+  fn file_waiter(version: usize) -> (FileWaiter, OneshotReceiver<Result<GetDiagnosticsResponse, AnyhowError>>) {
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    let file_waiter = FileWaiter {
+      uri: URI.to_owned(),
+      version,
+      filter: DiagnosticFilter::default(),
+      start_instant: Instant::now(),
+      timeout: Duration::from_secs(60),
+      sender,
+    };
+
+    (file_waiter, receiver)
+  }
+
+  fn file_progress(version: usize, is_processing: bool) -> FileProgress {
+    FileProgress {
+      version: Some(version),
+      is_processing,
+    }
+  }
+
+  fn file_diagnostics(version: usize, message: &str) -> FileDiagnostics {
+    let range = Range {
+      start: Position::new(0, 0),
+      end: Position::new(0, 1),
+    };
+    let diagnostic = Diagnostic {
+      range,
+      full_range: None,
+      severity: Some(1),
+      code: None,
+      source: None,
+      message: message.to_owned(),
+      tags: None,
+      related_information: None,
+      data: None,
+    };
+
+    FileDiagnostics {
+      version: Some(version),
+      diagnostics: vec![diagnostic],
+    }
+  }
+
+  fn messages(response: &GetDiagnosticsResponse) -> Vec<&str> {
+    response
+      .result
+      .iter()
+      .flat_map(|file_diagnostics| &file_diagnostics.diagnostics)
+      .map(|diagnostic| diagnostic.message.as_str())
+      .collect()
+  }
+
+  #[test]
+  fn waiter_resolves_once_the_version_is_processed() {
+    let mut file_reports = FileReports::default();
+    let (file_waiter, mut receiver) = file_waiter(2);
+
+    file_reports.wait(file_waiter);
+    file_reports.update_progress(URI.to_owned(), file_progress(1, false));
+    assert!(matches!(receiver.try_recv(), Err(TryRecvError::Empty)));
+
+    file_reports.update_progress(URI.to_owned(), file_progress(2, true));
+    assert!(matches!(receiver.try_recv(), Err(TryRecvError::Empty)));
+
+    file_reports.store_diagnostics(URI.to_owned(), file_diagnostics(2, "version 2"));
+    file_reports.update_progress(URI.to_owned(), file_progress(2, false));
+
+    let response = receiver
+      .try_recv()
+      .expect("waiter should resolve")
+      .expect("waiter should succeed");
+
+    assert_eq!(messages(&response), ["version 2"]);
+  }
+
+  #[test]
+  fn reopened_file_waits_for_new_progress() {
+    let mut file_reports = FileReports::default();
+
+    file_reports.store_diagnostics(URI.to_owned(), file_diagnostics(3, "previous buffer"));
+    file_reports.update_progress(URI.to_owned(), file_progress(3, false));
+
+    // NOTE: closing and then reopening the file at version 0
+    file_reports.forget(URI);
+    file_reports.forget(URI);
+
+    let (file_waiter, mut receiver) = file_waiter(0);
+
+    file_reports.wait(file_waiter);
+    assert!(matches!(receiver.try_recv(), Err(TryRecvError::Empty)));
+    assert!(
+      file_reports
+        .file_diagnostics(URI, &DiagnosticFilter::default())
+        .result
+        .is_none()
+    );
+
+    file_reports.update_progress(URI.to_owned(), file_progress(0, true));
+    assert!(matches!(receiver.try_recv(), Err(TryRecvError::Empty)));
+
+    file_reports.store_diagnostics(URI.to_owned(), file_diagnostics(0, "reopened buffer"));
+    file_reports.update_progress(URI.to_owned(), file_progress(0, false));
+
+    let response = receiver
+      .try_recv()
+      .expect("waiter should resolve")
+      .expect("waiter should succeed");
+
+    assert_eq!(messages(&response), ["reopened buffer"]);
+  }
+
+  #[test]
+  fn restart_forgets_reports_of_every_file() {
+    let mut file_reports = FileReports::default();
+
+    file_reports.store_diagnostics(URI.to_owned(), file_diagnostics(1, "before restart"));
+    file_reports.update_progress(URI.to_owned(), file_progress(1, false));
+    file_reports.forget_all();
+
+    let (file_waiter, mut receiver) = file_waiter(1);
+
+    file_reports.wait(file_waiter);
+    assert!(matches!(receiver.try_recv(), Err(TryRecvError::Empty)));
+    assert_eq!(file_reports.diagnostics(URI).count(), 0);
+  }
+
+  fn semantic_tokens_legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+      token_types: vec!["keyword".to_owned(), "variable".to_owned()],
+      token_modifiers: vec!["declaration".to_owned(), "readonly".to_owned()],
+    }
+  }
+
+  fn token_summary(token: &SemanticToken) -> (usize, usize, usize, &str, Vec<&str>) {
+    let token_modifiers = token.token_modifiers.iter().map(String::as_str).collect();
+
+    (
+      token.line,
+      token.start_character,
+      token.length,
+      token.token_type.as_str(),
+      token_modifiers,
+    )
+  }
+
+  #[test]
+  fn semantic_tokens_are_decoded_relative_to_the_previous_token() {
+    let data = [1, 2, 3, 0, 0, 0, 4, 1, 1, 1, 2, 5, 2, 0, 2];
+    let tokens = semantic_tokens_legend().decode(&data).expect("tokens should decode");
+    let token_summaries = tokens.iter().map(token_summary).collect::<Vec<_>>();
+
+    assert_eq!(
+      token_summaries,
+      [
+        (1, 2, 3, "keyword", vec![]),
+        (1, 6, 1, "variable", vec!["declaration"]),
+        (3, 5, 2, "keyword", vec!["readonly"]),
+      ]
+    );
+  }
+
+  #[test]
+  fn semantic_token_modifiers_outside_the_legend_are_ignored() {
+    let token_modifiers_bitset = (1 << 1) | (1 << 5) | (1 << (usize::BITS - 1));
+    let data = [0, 0, 1, 1, token_modifiers_bitset];
+    let tokens = semantic_tokens_legend().decode(&data).expect("tokens should decode");
+
+    assert_eq!(tokens[0].token_modifiers, ["readonly"]);
+  }
+
+  #[test]
+  fn invalid_semantic_tokens_are_errors() {
+    let legend = semantic_tokens_legend();
+
+    assert!(legend.decode(&[0, 0, 1, 2, 0]).is_err());
+    assert!(legend.decode(&[0, 0, 1, usize::MAX, 0]).is_err());
+    assert!(legend.decode(&[0, 0, 1, 0]).is_err());
+    assert!(legend.decode(&[usize::MAX, 0, 1, 0, 0, 1, 0, 1, 0, 0]).is_err());
+    assert!(legend.decode(&[0, usize::MAX, 1, 0, 0, 0, 1, 1, 0, 0]).is_err());
+  }
+
+  #[test]
+  fn lifecycle_requests_are_never_timed_out() {
+    let (sender, _receiver) = tokio::sync::oneshot::channel();
+    let lifecycle_requests = [
+      Request::Initialize(sender),
+      Request::Reinitialize,
+      Request::LeanRpcConnect {
+        uri: URI.to_owned(),
+        rpc_calls: Vec::new(),
+      },
+    ];
+
+    for request in lifecycle_requests {
+      assert!(!PendingRequest::new(request, Duration::ZERO.some()).is_timed_out());
+    }
+  }
+
+  #[test]
+  fn other_requests_are_timed_out() {
+    let (sender, _receiver) = tokio::sync::oneshot::channel();
+    let pending_request = PendingRequest::new(Request::Raw(sender), Duration::ZERO.some());
+
+    assert!(pending_request.is_timed_out());
+  }
+
+  #[test]
+  fn virtual_filepaths_are_resolved_against_the_project() {
+    let project_dirpath = Path::new("/project");
+    let virtual_filepaths = [
+      ("Scratch.lean", "/project/Scratch.lean"),
+      ("./Scratch/Main.lean", "/project/Scratch/Main.lean"),
+      ("/project/Scratch.lean", "/project/Scratch.lean"),
+    ];
+
+    for (filepath, virtual_filepath) in virtual_filepaths {
+      let filepath = SessionRunner::virtual_filepath(project_dirpath, Path::new(filepath).to_path_buf().some())
+        .expect("virtual filepath should be inside the project");
+
+      assert_eq!(filepath, Path::new(virtual_filepath));
+    }
+
+    let filepath =
+      SessionRunner::virtual_filepath(project_dirpath, None).expect("virtual filepath should be generated");
+
+    assert!(filepath.starts_with("/project/.lean-lsp"));
+  }
+
+  #[test]
+  fn virtual_filepaths_outside_the_project_are_errors() {
+    let project_dirpath = Path::new("/project");
+
+    for filepath in ["../Scratch.lean", "Scratch/../../Scratch.lean", "/other/Scratch.lean"] {
+      assert!(SessionRunner::virtual_filepath(project_dirpath, Path::new(filepath).to_path_buf().some()).is_err());
+    }
+  }
+
+  #[test]
+  fn lean_rpc_references_include_lazy_trace_children() {
+    let result = serde_json::json!([{
+      "msg": {
+        "tag": [{ "expr": { "tag": [{ "info": { "p": "1" } }, { "text": "x" }] } }, { "text": "" }],
+      },
+      "trace": {
+        "indent": 0,
+        "cls": "Meta.synthInstance",
+        "msg": { "text": "instance" },
+        "collapsed": true,
+        "children": { "lazy": { "p": "2" } },
+      },
+    }]);
+    let mut references = Vec::new();
+
+    SessionRunner::lean_rpc_references(&result, &mut references);
+
+    assert_eq!(
+      references,
+      [serde_json::json!({ "p": "1" }), serde_json::json!({ "p": "2" })]
+    );
+  }
+}
@@ -1,5 +1,3 @@
-use std::path::PathBuf;
-
 use anyhow::Error as AnyhowError;
 use derive_more::From;
 use mkutils::Utils;
@@ -26,13 +24,7 @@ impl SessionSet {
   }
 
   #[tracing::instrument(skip_all)]
-  pub async fn new_session(
-    &self,
-    lean_path: PathBuf,
-    lean_server_log_dirpath: Option<PathBuf>,
-  ) -> Result<Session, AnyhowError> {
-    let command = NewSessionCommand::new(lean_path, lean_server_log_dirpath);
-
+  pub async fn new_session(&self, command: NewSessionCommand) -> Result<Session, AnyhowError> {
     crate::macros::run_command!(self, SessionSetCommand::NewSession, command)
   }
 
@@ -1,4 +1,4 @@
-use std::{collections::HashMap, path::Path};
+use std::collections::HashMap;
 
 use anyhow::{Context, Error as AnyhowError};
 use mkutils::{Event, EventReceiver, EventSender, IntoStream, Utils};
@@ -9,7 +9,11 @@ use tokio::{
 use tokio_stream::wrappers::UnboundedReceiverStream as MpscUnboundedReceiverStream;
 use ulid::Ulid;
 
-use crate::{commands::SessionSetCommand, session::Session, session_runner::SessionResult};
+use crate::{
+  commands::{NewSessionCommand, SessionSetCommand},
+  session::Session,
+  session_runner::SessionResult,
+};
 
 pub struct SessionSetRunner {
   commands: MpscUnboundedReceiverStream<SessionSetCommand>,
@@ -35,8 +39,8 @@ impl SessionSetRunner {
     }
   }
 
-  fn new_session(&mut self, lean_path: &Path, lean_server_log_dirpath: Option<&Path>) -> Result<Session, AnyhowError> {
-    let (session, session_runner) = Session::new(lean_path, lean_server_log_dirpath)?;
+  fn new_session(&mut self, command: &NewSessionCommand) -> Result<Session, AnyhowError> {
+    let (session, session_runner) = Session::new(command)?;
 
     self.sessions.insert(session.id(), session.clone());
     self.session_results.spawn(session_runner.run());
@@ -68,9 +72,7 @@ impl SessionSetRunner {
   #[tracing::instrument(skip_all)]
   async fn process_command(&mut self, command: SessionSetCommand) -> Result<(), AnyhowError> {
     match command {
-      SessionSetCommand::NewSession { sender, command } => self
-        .new_session(command.lean_path.as_ref(), command.lean_server_log_dirpath.map_as_ref())
-        .send_to_oneshot(sender)?,
+      SessionSetCommand::NewSession { sender, command } => self.new_session(&command).send_to_oneshot(sender)?,
       SessionSetCommand::GetSessions { sender } => self.get_sessions().send_to_oneshot(sender)?,
       SessionSetCommand::GetSession { sender, session_id } => {
         self.get_session(session_id).send_to_oneshot(sender)?;
@@ -1,85 +1,379 @@
+use std::collections::VecDeque;
+
 use anyhow::Error as AnyhowError;
-use derive_more::Constructor;
+use futures::StreamExt;
 use mkutils::Utils;
 use poem::web::websocket::{Message as PoemMessage, WebSocketStream};
+use serde_json::Value as Json;
 use ulid::Ulid;
 
-use crate::{session::Session, session_set::SessionSet};
+use crate::{commands::NewSessionCommand, session::Session, session_set::SessionSet, types::DiagnosticFilter};
 
-#[derive(Constructor)]
 pub struct Stream {
   session_set: SessionSet,
   web_socket_stream: WebSocketStream,
+  pending_messages: VecDeque<PoemMessage>,
 }
 
 impl Stream {
+  pub fn new(session_set: SessionSet, web_socket_stream: WebSocketStream) -> Self {
+    let pending_messages = VecDeque::new();
+
+    Self {
+      session_set,
+      web_socket_stream,
+      pending_messages,
+    }
+  }
+
+  #[allow(clippy::too_many_lines)]
+  async fn response_json(session_set: &SessionSet, message: &str) -> Result<Json, AnyhowError> {
+    let mut message_json = message.to_json()?;
+    let session_id = message_json.take_json("session_id")?;
+    let response_json = match message_json.take_json::<String>("type")?.as_str() {
+      "new_session" => session_set
+        .new_session(message_json.to_value_from_value::<NewSessionCommand>()?)
+        .await?
+        .id()
+        .to_json_object("session_id"),
+      "get_sessions" => session_set
+        .get_sessions()
+        .await?
+        .iter()
+        .map_collect::<Ulid, Vec<_>>(Session::id)
+        .to_json_object("session_ids"),
+      "get_session" => session_set
+        .get_session(session_id)
+        .await?
+        .id()
+        .to_json_object("session_id"),
+      "initialize" => session_set
+        .get_session(session_id)
+        .await?
+        .initialize()
+        .await?
+        .with("complete")
+        .to_json_object("initialize"),
+      "open_file" => session_set
+        .get_session(session_id)
+        .await?
+        .open_file(message_json.take_json("filepath")?, message_json.take_json("text")?)
+        .await?
+        .to_json()?,
+      "edit_file" => session_set
+        .get_session(session_id)
+        .await?
+        .edit_file(message_json.take_json("filepath")?, message_json.take_json("edits")?)
+        .await?
+        .with("complete")
+        .to_json_object("edit_file"),
+      "get_file" => session_set
+        .get_session(session_id)
+        .await?
+        .get_file(message_json.take_json("filepath")?)
+        .await?
+        .to_json()?,
+      "save_file" => session_set
+        .get_session(session_id)
+        .await?
+        .save_file(message_json.take_json("filepath")?)
+        .await?
+        .with("complete")
+        .to_json_object("save_file"),
+      "close_file" => session_set
+        .get_session(session_id)
+        .await?
+        .close_file(message_json.take_json("filepath")?)
+        .await?
+        .with("complete")
+        .to_json_object("close_file"),
+      "get_plain_goals" => session_set
+        .get_session(session_id)
+        .await?
+        .get_plain_goals(message_json.take_json("location")?)
+        .await?
+        .to_json()?,
+      "get_plain_term_goal" => session_set
+        .get_session(session_id)
+        .await?
+        .get_plain_term_goal(message_json.take_json("location")?)
+        .await?
+        .to_json()?,
+      "get_interactive_goals" => session_set
+        .get_session(session_id)
+        .await?
+        .get_interactive_goals(
+          message_json.take_json("location")?,
+          message_json
+            .take_json::<Option<bool>>("keep_references")?
+            .unwrap_or_default(),
+        )
+        .await?
+        .to_json()?,
+      "get_definition" => session_set
+        .get_session(session_id)
+        .await?
+        .get_definition(message_json.take_json("location")?)
+        .await?
+        .to_json()?,
+      "get_declaration" => session_set
+        .get_session(session_id)
+        .await?
+        .get_declaration(message_json.take_json("location")?)
+        .await?
+        .to_json()?,
+      "get_type_definition" => session_set
+        .get_session(session_id)
+        .await?
+        .get_type_definition(message_json.take_json("location")?)
+        .await?
+        .to_json()?,
+      "get_references" => session_set
+        .get_session(session_id)
+        .await?
+        .get_references(
+          message_json.take_json("location")?,
+          message_json
+            .take_json::<Option<bool>>("include_declaration")?
+            .unwrap_or_default(),
+        )
+        .await?
+        .to_json()?,
+      "prepare_rename" => session_set
+        .get_session(session_id)
+        .await?
+        .prepare_rename(message_json.take_json("location")?)
+        .await?
+        .to_json()?,
+      "rename" => session_set
+        .get_session(session_id)
+        .await?
+        .rename(message_json.take_json("location")?, message_json.take_json("new_name")?)
+        .await?
+        .to_json()?,
+      "get_completions" => session_set
+        .get_session(session_id)
+        .await?
+        .get_completions(
+          message_json.take_json("location")?,
+          message_json.take_json("trigger_kind")?,
+          message_json.take_json("trigger_character")?,
+        )
+        .await?
+        .to_json()?,
+      "resolve_completion_item" => session_set
+        .get_session(session_id)
+        .await?
+        .resolve_completion_item(message_json.take_json("item")?)
+        .await?
+        .to_json()?,
+      "get_document_symbols" => session_set
+        .get_session(session_id)
+        .await?
+        .get_document_symbols(message_json.take_json("filepath")?)
+        .await?
+        .to_json()?,
+      "get_code_actions" => session_set
+        .get_session(session_id)
+        .await?
+        .get_code_actions(message_json.take_json("range_location")?)
+        .await?
+        .to_json()?,
+      "get_folding_ranges" => session_set
+        .get_session(session_id)
+        .await?
+        .get_folding_ranges(message_json.take_json("filepath")?)
+        .await?
+        .to_json()?,
+      "get_call_hierarchy" => session_set
+        .get_session(session_id)
+        .await?
+        .get_call_hierarchy(
+          message_json.take_json("location")?,
+          message_json.take_json::<Option<bool>>("outgoing")?.unwrap_or_default(),
+          message_json.take_json("depth")?,
+        )
+        .await?
+        .to_json()?,
+      "get_inlay_hints" => session_set
+        .get_session(session_id)
+        .await?
+        .get_inlay_hints(message_json.take_json("range_location")?)
+        .await?
+        .to_json()?,
+      "get_document_highlights" => session_set
+        .get_session(session_id)
+        .await?
+        .get_document_highlights(message_json.take_json("location")?)
+        .await?
+        .to_json()?,
+      "get_workspace_symbols" => session_set
+        .get_session(session_id)
+        .await?
+        .get_workspace_symbols(
+          message_json.take_json("query")?,
+          message_json.take_json("limit")?,
+          message_json
+            .take_json::<Option<Vec<usize>>>("kinds")?
+            .unwrap_or_default(),
+        )
+        .await?
+        .to_json()?,
+      "get_semantic_tokens" => session_set
+        .get_session(session_id)
+        .await?
+        .get_semantic_tokens(message_json.take_json("filepath")?)
+        .await?
+        .to_json()?,
+      "get_semantic_tokens_range" => session_set
+        .get_session(session_id)
+        .await?
+        .get_semantic_tokens_range(message_json.take_json("range_location")?)
+        .await?
+        .to_json()?,
+      "apply_workspace_edit" => session_set
+        .get_session(session_id)
+        .await?
+        .apply_workspace_edit(
+          message_json.take_json("workspace_edit")?,
+          message_json.take_json::<Option<bool>>("write")?.unwrap_or_default(),
+        )
+        .await?
+        .to_json()?,
+      "apply_code_action" => session_set
+        .get_session(session_id)
+        .await?
+        .apply_code_action(
+          message_json.take_json("action")?,
+          message_json.take_json::<Option<bool>>("write")?.unwrap_or_default(),
+        )
+        .await?
+        .to_json()?,
+      "get_diagnostics" => session_set
+        .get_session(session_id)
+        .await?
+        .get_diagnostics(
+          message_json.take_json("filepath")?,
+          message_json
+            .take_json::<Option<DiagnosticFilter>>("filter")?
+            .unwrap_or_default(),
+        )
+        .await?
+        .to_json()?,
+      "get_interactive_diagnostics" => session_set
+        .get_session(session_id)
+        .await?
+        .get_interactive_diagnostics(
+          message_json.take_json("filepath")?,
+          message_json.take_json("start_line")?,
+          message_json.take_json("end_line")?,
+          message_json
+            .take_json::<Option<bool>>("keep_references")?
+            .unwrap_or_default(),
+        )
+        .await?
+        .to_json()?,
+      "expand_trace_children" => session_set
+        .get_session(session_id)
+        .await?
+        .expand_trace_children(
+          message_json.take_json("filepath")?,
+          message_json.take_json("children")?,
+          message_json
+            .take_json::<Option<bool>>("keep_references")?
+            .unwrap_or_default(),
+        )
+        .await?
+        .to_json()?,
+      "wait_for_file" => session_set
+        .get_session(session_id)
+        .await?
+        .wait_for_file(
+          message_json.take_json("filepath")?,
+          message_json.take_json("version")?,
+          message_json.take_json("timeout_ms")?,
+          message_json
+            .take_json::<Option<DiagnosticFilter>>("filter")?
+            .unwrap_or_default(),
+        )
+        .await?
+        .to_json()?,
+      "reply_to_server_request" => session_set
+        .get_session(session_id)
+        .await?
+        .reply_to_server_request(
+          message_json.take_json("id")?,
+          message_json.take_json("result")?,
+          message_json.take_json("error")?,
+        )
+        .await?
+        .with("complete")
+        .to_json_object("reply_to_server_request"),
+      "send_raw_request" => session_set
+        .get_session(session_id)
+        .await?
+        .send_raw_request(message_json.take_json("method")?, message_json.take_json("params")?)
+        .await?
+        .to_json()?,
+      "send_raw_notification" => session_set
+        .get_session(session_id)
+        .await?
+        .send_raw_notification(message_json.take_json("method")?, message_json.take_json("params")?)
+        .await?
+        .with("complete")
+        .to_json_object("send_raw_notification"),
+      "call_lean_rpc" => session_set
+        .get_session(session_id)
+        .await?
+        .call_lean_rpc(
+          message_json.take_json("location")?,
+          message_json.take_json("method")?,
+          message_json.take_json("params")?,
+          message_json
+            .take_json::<Option<bool>>("keep_references")?
+            .unwrap_or_default(),
+        )
+        .await?
+        .to_json()?,
+      "release_lean_rpc_references" => session_set
+        .get_session(session_id)
+        .await?
+        .release_lean_rpc_references(message_json.take_json("filepath")?, message_json.take_json("refs")?)
+        .await?
+        .with("complete")
+        .to_json_object("release_lean_rpc_references"),
+      "get_status" => session_set.get_session(session_id).await?.status().await?.to_json()?,
+      _ => serde_json::json!({"error": "unknown type"}),
+    };
+
+    response_json.ok()
+  }
+
+  async fn next_message(&mut self) -> Result<PoemMessage, AnyhowError> {
+    match self.pending_messages.pop_front() {
+      Some(message) => message.ok(),
+      None => self.web_socket_stream.next_item_async().await??.ok(),
+    }
+  }
+
   pub async fn run(mut self) -> Result<(), AnyhowError> {
     loop {
-      let PoemMessage::Text(message) = self.web_socket_stream.next_item_async().await?? else { continue };
-      let mut message_json = message.to_json()?;
-      let session_id = message_json.take_json("session_id")?;
-      let response_json = match message_json.take_json::<String>("type")?.as_str() {
-        "new_session" => self
-          .session_set
-          .new_session(
-            message_json.take_json("lean_path")?,
-            message_json.take_json("lean_server_log_dirpath")?,
-          )
-          .await?
-          .id()
-          .to_json_object("session_id"),
-        "get_sessions" => self
-          .session_set
-          .get_sessions()
-          .await?
-          .iter()
-          .map_collect::<Ulid, Vec<_>>(Session::id)
-          .to_json_object("session_ids"),
-        "get_session" => self
-          .session_set
-          .get_session(session_id)
-          .await?
-          .id()
-          .to_json_object("session_id"),
-        "initialize" => self
-          .session_set
-          .get_session(session_id)
-          .await?
-          .initialize()
-          .await?
-          .with("complete")
-          .to_json_object("initialize"),
-        "open_file" => self
-          .session_set
-          .get_session(session_id)
-          .await?
-          .open_file(message_json.take_json("filepath")?)
-          .await?
-          .with("complete")
-          .to_json_object("open_file"),
-        "close_file" => self
-          .session_set
-          .get_session(session_id)
-          .await?
-          .close_file(message_json.take_json("filepath")?)
-          .await?
-          .with("complete")
-          .to_json_object("close_file"),
-        "get_plain_goals" => self
-          .session_set
-          .get_session(session_id)
-          .await?
-          .get_plain_goals(message_json.take_json("location")?)
-          .await?
-          .to_json()?,
-        "get_status" => self
-          .session_set
-          .get_session(session_id)
-          .await?
-          .status()
-          .await?
-          .to_json()?,
-        _ => serde_json::json!({"error": "unknown type"}),
+      let PoemMessage::Text(message) = self.next_message().await? else { continue };
+      let response_json_future = Self::response_json(&self.session_set, &message);
+
+      tokio::pin!(response_json_future);
+
+      // NOTE: keep reading from the web socket while the response is pending so
+      // that a disconnect drops the pending future, which in turn cancels
+      // any request it is waiting on
+      let response_json = loop {
+        tokio::select! {
+          response_json_res = &mut response_json_future => break response_json_res?,
+          message_res = self.web_socket_stream.next() => match message_res {
+            Some(Ok(PoemMessage::Close(_close))) | None => return ().ok(),
+            Some(message_res) => self.pending_messages.push_back(message_res?),
+          },
+        }
       };
 
       response_json
@@ -0,0 +1,66 @@
+use serde::Deserialize;
+use serde_json::Value as Json;
+
+use crate::types::{TaggedText, TaggedTextSpan};
+
+// NOTE: lean's `TaggedText` is one of `{"text": ..}`, `{"append": [..]}` or
+// `{"tag": [tag, child]}`
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LspTaggedText<T> {
+  Text(String),
+  Append(Vec<LspTaggedText<T>>),
+  Tag(T, Box<LspTaggedText<T>>),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LspSubexprInfo {
+  info: Json,
+  subexpr_pos: Option<String>,
+  diff_status: Option<String>,
+}
+
+impl LspTaggedText<LspSubexprInfo> {
+  // NOTE: spans are pushed when their tag is entered so that enclosing spans
+  // come before the spans nested in them
+  fn flatten(self, tagged_text: &mut TaggedText, length: &mut usize) {
+    match self {
+      Self::Text(text) => {
+        *length += text.chars().count();
+
+        tagged_text.text.push_str(&text);
+      }
+      Self::Append(children) => {
+        for child in children {
+          child.flatten(tagged_text, length);
+        }
+      }
+      Self::Tag(subexpr_info, child) => {
+        let index = tagged_text.spans.len();
+        let span = TaggedTextSpan {
+          start: *length,
+          end: *length,
+          subexpr_pos: subexpr_info.subexpr_pos,
+          diff_status: subexpr_info.diff_status,
+          info: subexpr_info.info,
+        };
+
+        tagged_text.spans.push(span);
+        child.flatten(tagged_text, length);
+
+        tagged_text.spans[index].end = *length;
+      }
+    }
+  }
+}
+
+impl From<LspTaggedText<LspSubexprInfo>> for TaggedText {
+  fn from(lsp_tagged_text: LspTaggedText<LspSubexprInfo>) -> Self {
+    let mut tagged_text = Self::default();
+
+    lsp_tagged_text.flatten(&mut tagged_text, &mut 0);
+
+    tagged_text
+  }
+}
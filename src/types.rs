@@ -1,11 +1,106 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, str::FromStr};
 
 use clap::Args;
 use derive_more::{Constructor, From};
+use mkutils::Utils;
 use poem_openapi::Object;
 use serde::{Deserialize, Serialize};
+use serde_json::Value as Json;
 use ulid::Ulid;
 
+#[derive(Clone, Deserialize, Object, Serialize)]
+pub struct EnvVar {
+  pub name: String,
+  pub value: String,
+}
+
+impl FromStr for EnvVar {
+  type Err = String;
+
+  fn from_str(env_var_str: &str) -> Result<Self, Self::Err> {
+    let Some((name, value)) = env_var_str.split_once('=') else {
+      return Err(std::format!(
+        "invalid environment variable {env_var_str:?}: expected NAME=VALUE"
+      ));
+    };
+
+    if name.is_empty() {
+      return Err(std::format!(
+        "invalid environment variable {env_var_str:?}: name is empty"
+      ));
+    }
+
+    let name = name.to_owned();
+    let value = value.to_owned();
+    let env_var = Self { name, value };
+
+    env_var.ok()
+  }
+}
+
+#[derive(Clone, Deserialize, Object, Serialize)]
+pub struct RequestTimeout {
+  pub method: Option<String>,
+  pub timeout_ms: u64,
+}
+
+impl FromStr for RequestTimeout {
+  type Err = String;
+
+  fn from_str(request_timeout_str: &str) -> Result<Self, Self::Err> {
+    let (method, timeout_ms_str) = match request_timeout_str.split_once('=') {
+      Some((method, timeout_ms_str)) => (method.some(), timeout_ms_str),
+      None => (None, request_timeout_str),
+    };
+
+    if method.is_some_and(str::is_empty) {
+      return Err(std::format!(
+        "invalid request timeout {request_timeout_str:?}: method is empty"
+      ));
+    }
+
+    let method = method.map(str::to_owned);
+    let Ok(timeout_ms) = timeout_ms_str.parse() else {
+      return Err(std::format!(
+        "invalid request timeout {request_timeout_str:?}: expected [METHOD=]MILLISECONDS"
+      ));
+    };
+    let request_timeout = Self { method, timeout_ms };
+
+    request_timeout.ok()
+  }
+}
+
+#[derive(Clone, Deserialize, Object, Serialize)]
+pub struct ServerRequestReply {
+  pub method: String,
+  pub result: Json,
+}
+
+impl FromStr for ServerRequestReply {
+  type Err = String;
+
+  fn from_str(server_request_reply_str: &str) -> Result<Self, Self::Err> {
+    let Some((method, result_str)) = server_request_reply_str.split_once('=') else {
+      return Err(std::format!(
+        "invalid server request reply {server_request_reply_str:?}: expected METHOD=JSON"
+      ));
+    };
+
+    if method.is_empty() {
+      return Err(std::format!(
+        "invalid server request reply {server_request_reply_str:?}: method is empty"
+      ));
+    }
+
+    let method = method.to_owned();
+    let result = serde_json::from_str(result_str).map_err(|error| error.to_string())?;
+    let server_request_reply = Self { method, result };
+
+    server_request_reply.ok()
+  }
+}
+
 #[derive(Deserialize, From, Object, Serialize)]
 pub struct TaskStatus {
   pub is_finished: bool,
@@ -22,12 +117,311 @@ pub struct Location {
   pub character: usize,
 }
 
+#[derive(Args, Constructor, Deserialize, Object, Serialize)]
+pub struct RangeLocation {
+  pub filepath: PathBuf,
+
+  #[arg(long)]
+  pub start_line: usize,
+
+  #[arg(long)]
+  pub start_character: usize,
+
+  #[arg(long)]
+  pub end_line: usize,
+
+  #[arg(long)]
+  pub end_character: usize,
+}
+
+impl RangeLocation {
+  pub fn range(&self) -> Range {
+    let start = Position::new(self.start_line, self.start_character);
+    let end = Position::new(self.end_line, self.end_character);
+
+    Range { start, end }
+  }
+}
+
+#[derive(Clone, Constructor, Deserialize, Eq, Object, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct Position {
+  pub line: usize,
+  pub character: usize,
+}
+
+#[derive(Clone, Deserialize, Object, Serialize)]
+pub struct Range {
+  pub start: Position,
+  pub end: Position,
+}
+
+impl Range {
+  pub fn overlaps(&self, other: &Self) -> bool {
+    self.start <= other.end && other.start <= self.end
+  }
+}
+
+// NOTE: filepaths are relative to the project dirpath unless they are outside
+// of it, e.g. for files in the lean toolchain
+#[derive(Deserialize, Object, Serialize)]
+pub struct FileRange {
+  pub filepath: PathBuf,
+  pub range: Range,
+}
+
+#[derive(Deserialize, Object, Serialize)]
+#[oai(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentSymbol {
+  pub name: String,
+  pub detail: Option<String>,
+  pub kind: usize,
+  pub range: Range,
+  pub selection_range: Range,
+  #[oai(default)]
+  #[serde(default)]
+  pub children: Vec<DocumentSymbol>,
+}
+
+#[derive(Deserialize, Object, Serialize)]
+#[oai(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase")]
+pub struct FoldingRange {
+  pub start_line: usize,
+  pub start_character: Option<usize>,
+  pub end_line: usize,
+  pub end_character: Option<usize>,
+  pub kind: Option<String>,
+}
+
+// NOTE: calls are the items that call this item for incoming calls and the
+// items that this item calls for outgoing calls, with the ranges of the calls
+// in the calling item
+#[derive(Deserialize, Object, Serialize)]
+pub struct CallHierarchyItem {
+  pub name: String,
+  pub kind: usize,
+  pub detail: Option<String>,
+  pub location: FileRange,
+  pub selection_range: Range,
+  pub from_ranges: Vec<Range>,
+  pub calls: Vec<CallHierarchyItem>,
+  pub is_recursive: bool,
+}
+
+// NOTE: the lsp item is kept as is to resolve the calls of the item with, as it
+// may carry data that the lean server needs to do so
+pub struct CallHierarchyCall {
+  pub lsp_item: Json,
+  pub item: CallHierarchyItem,
+}
+
+#[derive(Deserialize, Object, Serialize)]
+pub struct WorkspaceSymbol {
+  pub name: String,
+  pub kind: usize,
+  pub container_name: Option<String>,
+  pub location: FileRange,
+}
+
+#[derive(Deserialize, Object, Serialize)]
+pub struct SemanticToken {
+  pub line: usize,
+  pub start_character: usize,
+  pub length: usize,
+  pub token_type: String,
+  pub token_modifiers: Vec<String>,
+}
+
+// NOTE: also deserializes from lsp text edits, which name the text `newText`
+#[derive(Clone, Deserialize, Object, Serialize)]
+pub struct TextEdit {
+  pub range: Range,
+  #[serde(alias = "newText")]
+  pub text: String,
+}
+
+#[derive(Deserialize, Object, Serialize)]
+pub struct InlayHint {
+  pub position: Position,
+  pub label: String,
+  pub kind: Option<usize>,
+  pub padding_left: bool,
+  pub padding_right: bool,
+  pub text_edits: Vec<TextEdit>,
+}
+
+#[derive(Deserialize, Object, Serialize)]
+pub struct DocumentHighlight {
+  pub range: Range,
+  pub kind: Option<usize>,
+}
+
+#[derive(Deserialize, Object, Serialize)]
+pub struct FileDiff {
+  pub filepath: PathBuf,
+  pub diff: String,
+}
+
+#[derive(Clone, Deserialize, Object, Serialize)]
+#[oai(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase")]
+pub struct Diagnostic {
+  pub range: Range,
+  pub full_range: Option<Range>,
+  pub severity: Option<usize>,
+  pub code: Option<Json>,
+  pub source: Option<String>,
+  pub message: String,
+  pub tags: Option<Vec<usize>>,
+  pub related_information: Option<Json>,
+  pub data: Option<Json>,
+}
+
+#[derive(Clone, Deserialize, Object, Serialize)]
+pub struct FileDiagnostics {
+  pub version: Option<usize>,
+  pub diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(Args, Default, Deserialize, Object, Serialize)]
+pub struct DiagnosticFilter {
+  /// Only include diagnostics at least this severe: 1 (error), 2 (warning), 3
+  /// (information) or 4 (hint)
+  #[arg(long)]
+  pub severity: Option<usize>,
+
+  #[arg(long)]
+  pub start_line: Option<usize>,
+
+  #[arg(long)]
+  pub end_line: Option<usize>,
+}
+
+impl DiagnosticFilter {
+  // NOTE: the lsp leaves the interpretation of a missing severity to the
+  // client, so treat it as an error
+  const DEFAULT_SEVERITY: usize = 1;
+
+  pub fn matches(&self, diagnostic: &Diagnostic) -> bool {
+    let severity = diagnostic.severity.unwrap_or(Self::DEFAULT_SEVERITY);
+    let matches_severity = self.severity.is_none_or(|max_severity| severity <= max_severity);
+    let matches_start_line = self
+      .start_line
+      .is_none_or(|start_line| start_line <= diagnostic.range.end.line);
+    let matches_end_line = self
+      .end_line
+      .is_none_or(|end_line| diagnostic.range.start.line <= end_line);
+
+    matches_severity && matches_start_line && matches_end_line
+  }
+
+  pub fn filter(&self, file_diagnostics: &FileDiagnostics) -> FileDiagnostics {
+    let version = file_diagnostics.version;
+    let diagnostics = file_diagnostics
+      .diagnostics
+      .iter()
+      .filter(|diagnostic| self.matches(diagnostic))
+      .cloned()
+      .collect();
+
+    FileDiagnostics { version, diagnostics }
+  }
+}
+
 #[derive(Deserialize, Object, Serialize)]
 pub struct PlainGoals {
   pub goals: Vec<String>,
   pub rendered: String,
 }
 
+#[derive(Deserialize, Object, Serialize)]
+pub struct PlainTermGoal {
+  pub goal: String,
+  pub range: Range,
+}
+
+// NOTE: each span marks a subexpression of the pretty-printed text, with
+// `start` and `end` counted in characters of `text`
+#[derive(Default, Deserialize, Object, Serialize)]
+pub struct TaggedText {
+  pub text: String,
+  pub spans: Vec<TaggedTextSpan>,
+}
+
+#[derive(Deserialize, Object, Serialize)]
+pub struct TaggedTextSpan {
+  pub start: usize,
+  pub end: usize,
+  pub subexpr_pos: Option<String>,
+  pub diff_status: Option<String>,
+  /// Reference to the lean server's info about the subexpression, which only
+  /// stays valid while its references are kept.
+  pub info: Json,
+}
+
+#[derive(Deserialize, Object, Serialize)]
+pub struct Hypothesis {
+  pub names: Vec<String>,
+  pub type_text: TaggedText,
+  pub value_text: Option<TaggedText>,
+  pub is_inserted: bool,
+  pub is_removed: bool,
+}
+
+#[derive(Deserialize, Object, Serialize)]
+pub struct InteractiveGoal {
+  pub user_name: Option<String>,
+  /// The user name without macro scopes, as shown after `case`.
+  pub case_tag: Option<String>,
+  pub goal_prefix: String,
+  pub hyps: Vec<Hypothesis>,
+  pub target: TaggedText,
+  pub is_inserted: bool,
+  pub is_removed: bool,
+}
+
+#[derive(Deserialize, From, Object, Serialize)]
+pub struct InteractiveGoals {
+  pub goals: Vec<InteractiveGoal>,
+}
+
+// NOTE: exactly one of the fields of a node is set
+#[derive(Default, Deserialize, Object, Serialize)]
+pub struct MessageNode {
+  pub text: Option<String>,
+  pub expr: Option<TaggedText>,
+  pub goal: Option<InteractiveGoal>,
+  pub trace: Option<TraceNode>,
+}
+
+#[derive(Default, Deserialize, From, Object, Serialize)]
+pub struct InteractiveMessage {
+  pub nodes: Vec<MessageNode>,
+}
+
+#[derive(Deserialize, Object, Serialize)]
+pub struct TraceNode {
+  pub class: String,
+  pub indent: usize,
+  pub collapsed: bool,
+  pub message: InteractiveMessage,
+  pub children: Vec<InteractiveMessage>,
+  /// Reference to expand the children with while they have not been loaded,
+  /// which is only kept alive if references were kept.
+  pub lazy_children: Option<Json>,
+}
+
+#[derive(Deserialize, Object, Serialize)]
+pub struct InteractiveDiagnostic {
+  pub range: Range,
+  pub full_range: Option<Range>,
+  pub severity: Option<usize>,
+  pub code: Option<Json>,
+  pub source: Option<String>,
+  pub message: InteractiveMessage,
+}
+
 #[derive(Deserialize, Object, Serialize)]
 pub struct SessionStatus {
   pub id: Ulid,
@@ -40,3 +434,212 @@ pub struct SessionSetStatus {
   session_set: TaskStatus,
   sessions: Vec<SessionStatus>,
 }
+
+#[cfg(test)]
+mod tests {
+  use super::{
+    Diagnostic, DiagnosticFilter, EnvVar, FileDiagnostics, Position, Range, RequestTimeout, ServerRequestReply,
+  };
+
+  fn diagnostic(severity: Option<usize>, start_line: usize, end_line: usize) -> Diagnostic {
+    let range = Range {
+      start: Position::new(start_line, 0),
+      end: Position::new(end_line, 0),
+    };
+
+    Diagnostic {
+      range,
+      full_range: None,
+      severity,
+      code: None,
+      source: None,
+      message: format!("lines {start_line}-{end_line}"),
+      tags: None,
+      related_information: None,
+      data: None,
+    }
+  }
+
+  fn filter(severity: Option<usize>, start_line: Option<usize>, end_line: Option<usize>) -> DiagnosticFilter {
+    DiagnosticFilter {
+      severity,
+      start_line,
+      end_line,
+    }
+  }
+
+  #[test]
+  fn default_filter_matches_everything() {
+    let filter = DiagnosticFilter::default();
+
+    for severity in [None, Some(1), Some(2), Some(3), Some(4)] {
+      assert!(filter.matches(&diagnostic(severity, 0, 0)));
+    }
+  }
+
+  #[test]
+  fn severity_keeps_diagnostics_at_least_as_severe() {
+    let filter = filter(Some(2), None, None);
+
+    assert!(filter.matches(&diagnostic(Some(1), 0, 0)));
+    assert!(filter.matches(&diagnostic(Some(2), 0, 0)));
+    assert!(!filter.matches(&diagnostic(Some(3), 0, 0)));
+    assert!(!filter.matches(&diagnostic(Some(4), 0, 0)));
+  }
+
+  #[test]
+  fn missing_severity_counts_as_an_error() {
+    assert!(filter(Some(1), None, None).matches(&diagnostic(None, 0, 0)));
+  }
+
+  #[test]
+  fn line_range_keeps_overlapping_diagnostics() {
+    let filter = filter(None, Some(10), Some(20));
+
+    assert!(!filter.matches(&diagnostic(None, 0, 9)));
+    assert!(filter.matches(&diagnostic(None, 0, 10)));
+    assert!(filter.matches(&diagnostic(None, 12, 15)));
+    assert!(filter.matches(&diagnostic(None, 5, 25)));
+    assert!(filter.matches(&diagnostic(None, 20, 30)));
+    assert!(!filter.matches(&diagnostic(None, 21, 30)));
+  }
+
+  #[test]
+  fn line_range_bounds_are_independent() {
+    assert!(filter(None, Some(10), None).matches(&diagnostic(None, 100, 100)));
+    assert!(!filter(None, Some(10), None).matches(&diagnostic(None, 0, 9)));
+    assert!(filter(None, None, Some(10)).matches(&diagnostic(None, 0, 0)));
+    assert!(!filter(None, None, Some(10)).matches(&diagnostic(None, 11, 11)));
+  }
+
+  #[test]
+  fn filter_keeps_version_and_order() {
+    let file_diagnostics = FileDiagnostics {
+      version: Some(3),
+      diagnostics: vec![
+        diagnostic(Some(1), 0, 0),
+        diagnostic(Some(3), 1, 1),
+        diagnostic(Some(2), 2, 2),
+      ],
+    };
+    let filtered = filter(Some(2), None, None).filter(&file_diagnostics);
+    let messages = filtered
+      .diagnostics
+      .iter()
+      .map(|diagnostic| diagnostic.message.as_str())
+      .collect::<Vec<_>>();
+
+    assert_eq!(filtered.version, Some(3));
+    assert_eq!(messages, ["lines 0-0", "lines 2-2"]);
+  }
+
+  #[test]
+  fn env_vars_are_parsed() {
+    let env_vars = [
+      ("LEAN_PATH=/lib", "LEAN_PATH", "/lib"),
+      ("EMPTY=", "EMPTY", ""),
+      ("OPTIONS=a=b", "OPTIONS", "a=b"),
+    ];
+
+    for (env_var_str, name, value) in env_vars {
+      let env_var = env_var_str.parse::<EnvVar>().expect("env var should parse");
+
+      assert_eq!((env_var.name.as_str(), env_var.value.as_str()), (name, value));
+    }
+  }
+
+  #[test]
+  fn malformed_env_vars_are_errors() {
+    for env_var_str in ["", "LEAN_PATH", "=/lib", "="] {
+      assert!(
+        env_var_str.parse::<EnvVar>().is_err(),
+        "{env_var_str:?} should not parse"
+      );
+    }
+  }
+
+  #[test]
+  fn request_timeouts_are_parsed() {
+    let request_timeouts = [
+      ("1500", None, 1500),
+      ("textDocument/hover=250", Some("textDocument/hover"), 250),
+      ("$/lean/rpc/call=0", Some("$/lean/rpc/call"), 0),
+    ];
+
+    for (request_timeout_str, method, timeout_ms) in request_timeouts {
+      let request_timeout = request_timeout_str
+        .parse::<RequestTimeout>()
+        .expect("request timeout should parse");
+
+      assert_eq!(
+        (request_timeout.method.as_deref(), request_timeout.timeout_ms),
+        (method, timeout_ms)
+      );
+    }
+  }
+
+  #[test]
+  fn malformed_request_timeouts_are_errors() {
+    let request_timeout_strs = [
+      "",
+      "fast",
+      "-1",
+      "1.5",
+      "=250",
+      "textDocument/hover=",
+      "textDocument/hover=soon",
+    ];
+
+    for request_timeout_str in request_timeout_strs {
+      assert!(
+        request_timeout_str.parse::<RequestTimeout>().is_err(),
+        "{request_timeout_str:?} should not parse"
+      );
+    }
+  }
+
+  #[test]
+  fn server_request_replies_are_parsed() {
+    let server_request_replies = [
+      (
+        "workspace/configuration=[null]",
+        "workspace/configuration",
+        serde_json::json!([null]),
+      ),
+      (
+        "window/showDocument={\"success\":true}",
+        "window/showDocument",
+        serde_json::json!({ "success": true }),
+      ),
+      ("custom/method=\"a=b\"", "custom/method", serde_json::json!("a=b")),
+    ];
+
+    for (server_request_reply_str, method, result) in server_request_replies {
+      let server_request_reply = server_request_reply_str
+        .parse::<ServerRequestReply>()
+        .expect("server request reply should parse");
+
+      assert_eq!(server_request_reply.method, method);
+      assert_eq!(server_request_reply.result, result);
+    }
+  }
+
+  #[test]
+  fn malformed_server_request_replies_are_errors() {
+    let server_request_reply_strs = [
+      "",
+      "workspace/configuration",
+      "=null",
+      "workspace/configuration=",
+      "workspace/configuration=nul",
+      "workspace/configuration={\"a\":}",
+    ];
+
+    for server_request_reply_str in server_request_reply_strs {
+      assert!(
+        server_request_reply_str.parse::<ServerRequestReply>().is_err(),
+        "{server_request_reply_str:?} should not parse"
+      );
+    }
+  }
+}
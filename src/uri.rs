@@ -0,0 +1,88 @@
+use std::path::PathBuf;
+
+use anyhow::Error as AnyhowError;
+use mkutils::Utils;
+
+const FILE_URI_PREFIX: &str = "file://";
+const PERCENT_ENCODING_RADIX: u32 = 16;
+
+// NOTE: inverse of Utils::to_uri() for file uris; percent-encoded bytes are
+// decoded and the result must be valid utf-8
+pub fn filepath(uri: &str) -> Result<PathBuf, AnyhowError> {
+  let Some(encoded_path) = uri.strip_prefix(FILE_URI_PREFIX) else {
+    anyhow::bail!("unsupported uri {uri}: only file uris are supported");
+  };
+  let encoded_bytes = encoded_path.as_bytes();
+  let mut bytes = Vec::with_capacity(encoded_bytes.len());
+  let mut index = 0;
+
+  while index < encoded_bytes.len() {
+    if encoded_bytes[index] == b'%'
+      && let Some(hex) = encoded_path.get(index + 1..index + 3)
+      && let Ok(byte) = u8::from_str_radix(hex, PERCENT_ENCODING_RADIX)
+    {
+      bytes.push(byte);
+      index += 3;
+    } else {
+      bytes.push(encoded_bytes[index]);
+      index += 1;
+    }
+  }
+
+  PathBuf::from(String::from_utf8(bytes)?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+  use std::path::PathBuf;
+
+  use mkutils::Utils;
+  use ulid::Ulid;
+
+  #[test]
+  fn percent_encoded_spaces_are_decoded() {
+    let filepath = super::filepath("file:///project/My%20Theorems/Main%20File.lean").expect("uri should decode");
+
+    assert_eq!(filepath, PathBuf::from("/project/My Theorems/Main File.lean"));
+  }
+
+  #[test]
+  fn percent_encoded_non_ascii_characters_are_decoded() {
+    let filepath = super::filepath("file:///project/Th%C3%A9or%C3%A8me/%F0%9D%94%B8.lean").expect("uri should decode");
+
+    assert_eq!(filepath, PathBuf::from("/project/Théorème/𝔸.lean"));
+  }
+
+  #[test]
+  fn unencoded_characters_are_kept() {
+    let filepath = super::filepath("file:///project/Théorème/100%.lean").expect("uri should decode");
+
+    assert_eq!(filepath, PathBuf::from("/project/Théorème/100%.lean"));
+  }
+
+  #[test]
+  fn invalid_uris_are_errors() {
+    assert!(super::filepath("https://example.com/Main.lean").is_err());
+    assert!(super::filepath("/project/Main.lean").is_err());
+    assert!(super::filepath("file:///project/%FF.lean").is_err());
+  }
+
+  #[test]
+  fn file_uris_round_trip() {
+    let dirpath = std::env::temp_dir().join(format!("{} Théorème 100%", Ulid::new()));
+    let filepath = dirpath.join("Main File.lean");
+
+    std::fs::create_dir_all(&dirpath).expect("temp dir should be created");
+    std::fs::write(&filepath, "").expect("temp file should be written");
+
+    // NOTE: the temp dir may be behind a symlink
+    let filepath = std::fs::canonicalize(&filepath).expect("temp file should exist");
+    let uri = filepath.to_uri().expect("filepath should encode");
+    let decoded_filepath = super::filepath(&uri).expect("uri should decode");
+
+    std::fs::remove_dir_all(&dirpath).expect("temp dir should be removed");
+
+    assert!(uri.starts_with("file:///"));
+    assert_eq!(decoded_filepath, filepath);
+  }
+}
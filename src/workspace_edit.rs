@@ -0,0 +1,632 @@
+use std::{
+  collections::HashMap,
+  io::ErrorKind,
+  path::{Path, PathBuf},
+};
+
+use anyhow::Error as AnyhowError;
+use mkutils::{ToValue, Utils};
+use serde::Deserialize;
+use serde_json::Value as Json;
+use similar::TextDiff;
+
+use crate::{
+  document::Document,
+  types::{FileDiff, Range, TextEdit},
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LspTextEdit {
+  range: Range,
+  new_text: String,
+}
+
+#[derive(Deserialize)]
+struct OptionalVersionedTextDocumentIdentifier {
+  uri: String,
+  version: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TextDocumentEdit {
+  text_document: OptionalVersionedTextDocumentIdentifier,
+  edits: Vec<LspTextEdit>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct FileOperationOptions {
+  overwrite: bool,
+  ignore_if_exists: bool,
+  recursive: bool,
+  ignore_if_not_exists: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+enum ResourceOperation {
+  Create {
+    uri: String,
+    #[serde(default)]
+    options: FileOperationOptions,
+  },
+  #[serde(rename_all = "camelCase")]
+  Rename {
+    old_uri: String,
+    new_uri: String,
+    #[serde(default)]
+    options: FileOperationOptions,
+  },
+  Delete {
+    uri: String,
+    #[serde(default)]
+    options: FileOperationOptions,
+  },
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DocumentChange {
+  ResourceOperation(ResourceOperation),
+  TextDocumentEdit(TextDocumentEdit),
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WorkspaceEdit {
+  #[serde(default)]
+  changes: HashMap<String, Vec<LspTextEdit>>,
+  document_changes: Option<Vec<DocumentChange>>,
+}
+
+// NOTE: `None` means that the file does not exist
+struct PlannedFile {
+  original_text: Option<String>,
+  text: Option<String>,
+}
+
+enum PlannedChange {
+  File(PathBuf),
+  DeletedDirectory(PathBuf),
+}
+
+#[derive(Clone, Copy)]
+pub struct FileChange<'a> {
+  pub filepath: &'a Path,
+  pub original_text: Option<&'a str>,
+  pub text: Option<&'a str>,
+}
+
+impl FileChange<'_> {
+  pub fn reverted(&self) -> Self {
+    Self {
+      filepath: self.filepath,
+      original_text: self.text,
+      text: self.original_text,
+    }
+  }
+}
+
+pub enum WorkspaceChange<'a> {
+  File(FileChange<'a>),
+  DeletedDirectory(&'a Path),
+}
+
+// NOTE: the whole edit is planned against in-memory copies of the affected
+// files before anything is changed, so that an invalid edit leaves every file
+// untouched; changes are then made in the order in which the edit first
+// touches each path, and file changes are reverted if making one fails, per
+// the textOnlyTransactional failure handling
+pub struct WorkspaceEditPlan {
+  files: HashMap<PathBuf, PlannedFile>,
+  changes: Vec<PlannedChange>,
+}
+
+impl WorkspaceEditPlan {
+  const NULL_FILEPATH: &'static str = "/dev/null";
+
+  pub async fn new(workspace_edit: &Json, open_documents: &HashMap<PathBuf, &Document>) -> Result<Self, AnyhowError> {
+    // NOTE: a null workspace edit, e.g. from a rename without any references,
+    // changes nothing
+    let workspace_edit = workspace_edit
+      .to_value_from_value::<Option<WorkspaceEdit>>()?
+      .unwrap_or_default();
+    let mut workspace_edit_plan = Self {
+      files: HashMap::new(),
+      changes: Vec::new(),
+    };
+
+    // NOTE: document changes take precedence over changes when both are given
+    match workspace_edit.document_changes {
+      Some(document_changes) => {
+        for document_change in document_changes {
+          match document_change {
+            DocumentChange::TextDocumentEdit(text_document_edit) => {
+              workspace_edit_plan
+                .add_text_edits(
+                  &text_document_edit.text_document.uri,
+                  text_document_edit.text_document.version,
+                  text_document_edit.edits,
+                  open_documents,
+                )
+                .await?;
+            }
+            DocumentChange::ResourceOperation(resource_operation) => {
+              workspace_edit_plan
+                .add_resource_operation(resource_operation, open_documents)
+                .await?;
+            }
+          }
+        }
+      }
+      None => {
+        for (uri, edits) in workspace_edit.changes {
+          workspace_edit_plan
+            .add_text_edits(&uri, None, edits, open_documents)
+            .await?;
+        }
+      }
+    }
+
+    workspace_edit_plan.ok()
+  }
+
+  async fn read_text(
+    filepath: &Path,
+    open_documents: &HashMap<PathBuf, &Document>,
+  ) -> Result<Option<String>, AnyhowError> {
+    if let Some(document) = open_documents.get(filepath) {
+      return document.text.clone().some().ok();
+    }
+
+    match tokio::fs::read_to_string(filepath).await {
+      Ok(text) => text.some().ok(),
+      Err(error) if error.kind() == ErrorKind::NotFound => None.ok(),
+      Err(error) => Err(error.into()),
+    }
+  }
+
+  fn deleted_dirpaths(&self) -> impl Iterator<Item = &Path> {
+    self.changes.iter().filter_map(|planned_change| match planned_change {
+      PlannedChange::DeletedDirectory(dirpath) => dirpath.as_path().some(),
+      PlannedChange::File(_filepath) => None,
+    })
+  }
+
+  // NOTE: a path under a deleted directory can neither be changed before the
+  // directory is deleted nor after it
+  fn ensure_not_deleted(&self, path: &Path) -> Result<(), AnyhowError> {
+    if let Some(dirpath) = self.deleted_dirpaths().find(|dirpath| path.starts_with(dirpath)) {
+      anyhow::bail!(
+        "unable to change {}: directory {} is deleted",
+        path.display(),
+        dirpath.display()
+      );
+    }
+
+    ().ok()
+  }
+
+  async fn file(
+    &mut self,
+    filepath: &Path,
+    open_documents: &HashMap<PathBuf, &Document>,
+  ) -> Result<&mut PlannedFile, AnyhowError> {
+    self.ensure_not_deleted(filepath)?;
+
+    if !self.files.contains_key(filepath) {
+      let original_text = Self::read_text(filepath, open_documents).await?;
+      let text = original_text.clone();
+
+      self
+        .files
+        .insert(filepath.to_path_buf(), PlannedFile { original_text, text });
+      self.changes.push(PlannedChange::File(filepath.to_path_buf()));
+    }
+
+    match self.files.get_mut(filepath) {
+      Some(planned_file) => planned_file.ok(),
+      None => anyhow::bail!("file {} was not planned", filepath.display()),
+    }
+  }
+
+  // NOTE: the edits of a workspace edit all refer to the original text, so they
+  // are applied from the end of the text to its start, with edits at the same
+  // position applied in reverse so that they end up in their original order;
+  // an edit may only start where the edit before it ends, as the later edit
+  // would otherwise be applied to text that the earlier one has replaced
+  fn sequential_edits(edits: Vec<LspTextEdit>) -> Result<Vec<TextEdit>, AnyhowError> {
+    let mut edits = edits.into_iter().enumerate().collect::<Vec<_>>();
+
+    edits.sort_by(|(index_1, edit_1), (index_2, edit_2)| {
+      (&edit_2.range.start, index_2).cmp(&(&edit_1.range.start, index_1))
+    });
+
+    for edit_pair in edits.windows(2) {
+      let [(_index_1, edit_1), (_index_2, edit_2)] = edit_pair else { continue };
+
+      if edit_1.range.start < edit_2.range.end {
+        anyhow::bail!("text edits overlap");
+      }
+    }
+
+    edits
+      .into_iter()
+      .map(|(_index, edit)| TextEdit {
+        range: edit.range,
+        text: edit.new_text,
+      })
+      .collect::<Vec<_>>()
+      .ok()
+  }
+
+  async fn add_text_edits(
+    &mut self,
+    uri: &str,
+    version: Option<usize>,
+    edits: Vec<LspTextEdit>,
+    open_documents: &HashMap<PathBuf, &Document>,
+  ) -> Result<(), AnyhowError> {
+    let filepath = crate::uri::filepath(uri)?;
+
+    if let Some(version) = version
+      && let Some(document) = open_documents.get(&filepath)
+      && document.version != version
+    {
+      anyhow::bail!(
+        "edit for version {version} of {} does not match its open version {}",
+        filepath.display(),
+        document.version,
+      );
+    }
+
+    let planned_file = self.file(&filepath, open_documents).await?;
+    let Some(text) = &planned_file.text else {
+      anyhow::bail!("unable to edit {}: file does not exist", filepath.display());
+    };
+    let text = Document::edit_text(text, &Self::sequential_edits(edits)?)?;
+
+    planned_file.text = text.some();
+
+    ().ok()
+  }
+
+  fn ensure_not_open(filepath: &Path, open_documents: &HashMap<PathBuf, &Document>) -> Result<(), AnyhowError> {
+    if open_documents.contains_key(filepath) {
+      anyhow::bail!("unable to move or delete {}: file is open", filepath.display());
+    }
+
+    ().ok()
+  }
+
+  async fn add_resource_operation(
+    &mut self,
+    resource_operation: ResourceOperation,
+    open_documents: &HashMap<PathBuf, &Document>,
+  ) -> Result<(), AnyhowError> {
+    match resource_operation {
+      ResourceOperation::Create { uri, options } => {
+        let filepath = crate::uri::filepath(&uri)?;
+        let planned_file = self.file(&filepath, open_documents).await?;
+
+        // NOTE: overwrite wins over ignore if exists
+        if planned_file.text.is_some() && !options.overwrite {
+          if options.ignore_if_exists {
+            return ().ok();
+          }
+
+          anyhow::bail!("unable to create {}: file already exists", filepath.display());
+        }
+
+        planned_file.text = String::new().some();
+      }
+      ResourceOperation::Rename {
+        old_uri,
+        new_uri,
+        options,
+      } => {
+        let old_filepath = crate::uri::filepath(&old_uri)?;
+        let new_filepath = crate::uri::filepath(&new_uri)?;
+
+        Self::ensure_not_open(&old_filepath, open_documents)?;
+
+        if tokio::fs::metadata(&old_filepath)
+          .await
+          .is_ok_and(|metadata| metadata.is_dir())
+        {
+          anyhow::bail!(
+            "unable to rename {}: renaming directories is not supported",
+            old_filepath.display()
+          );
+        }
+
+        let new_file_exists = self.file(&new_filepath, open_documents).await?.text.is_some();
+
+        if new_file_exists && !options.overwrite {
+          if options.ignore_if_exists {
+            return ().ok();
+          }
+
+          anyhow::bail!("unable to rename to {}: file already exists", new_filepath.display());
+        }
+
+        let old_planned_file = self.file(&old_filepath, open_documents).await?;
+        let Some(text) = old_planned_file.text.take() else {
+          anyhow::bail!("unable to rename {}: file does not exist", old_filepath.display());
+        };
+
+        self.file(&new_filepath, open_documents).await?.text = text.some();
+      }
+      ResourceOperation::Delete { uri, options } => {
+        let filepath = crate::uri::filepath(&uri)?;
+
+        Self::ensure_not_open(&filepath, open_documents)?;
+
+        if tokio::fs::metadata(&filepath)
+          .await
+          .is_ok_and(|metadata| metadata.is_dir())
+        {
+          if !options.recursive {
+            anyhow::bail!(
+              "unable to delete directory {}: delete is not recursive",
+              filepath.display()
+            );
+          }
+
+          self.ensure_not_deleted(&filepath)?;
+
+          if let Some(changed_filepath) = self
+            .files
+            .keys()
+            .find(|changed_filepath| changed_filepath.starts_with(&filepath))
+          {
+            anyhow::bail!(
+              "unable to delete directory {}: {} is changed",
+              filepath.display(),
+              changed_filepath.display()
+            );
+          }
+
+          self.changes.push(PlannedChange::DeletedDirectory(filepath));
+
+          return ().ok();
+        }
+
+        let planned_file = self.file(&filepath, open_documents).await?;
+
+        if planned_file.text.is_none() && !options.ignore_if_not_exists {
+          anyhow::bail!("unable to delete {}: file does not exist", filepath.display());
+        }
+
+        planned_file.text = None;
+      }
+    }
+
+    ().ok()
+  }
+
+  fn file_change<'a>(&'a self, filepath: &'a Path) -> Option<FileChange<'a>> {
+    let planned_file = self.files.get(filepath)?;
+
+    if planned_file.text == planned_file.original_text {
+      return None;
+    }
+
+    FileChange {
+      filepath,
+      original_text: planned_file.original_text.as_deref(),
+      text: planned_file.text.as_deref(),
+    }
+    .some()
+  }
+
+  pub fn changes(&self) -> impl Iterator<Item = WorkspaceChange<'_>> {
+    self.changes.iter().filter_map(|planned_change| match planned_change {
+      PlannedChange::File(filepath) => self.file_change(filepath).map(WorkspaceChange::File),
+      PlannedChange::DeletedDirectory(dirpath) => WorkspaceChange::DeletedDirectory(dirpath).some(),
+    })
+  }
+
+  fn diff_header(filepath: &Path, text: Option<&str>) -> String {
+    match text {
+      Some(_text) => filepath.display().to_string(),
+      None => Self::NULL_FILEPATH.to_owned(),
+    }
+  }
+
+  // NOTE: filepaths are made relative to the project dirpath, as for locations
+  pub fn diffs(&self, project_dirpath: &Path) -> Vec<FileDiff> {
+    self
+      .changes()
+      .filter_map(|workspace_change| match workspace_change {
+        WorkspaceChange::File(file_change) => file_change.some(),
+        WorkspaceChange::DeletedDirectory(_dirpath) => None,
+      })
+      .map(|file_change| {
+        let filepath = file_change
+          .filepath
+          .strip_prefix(project_dirpath)
+          .unwrap_or(file_change.filepath)
+          .to_path_buf();
+        let old_header = Self::diff_header(&filepath, file_change.original_text);
+        let new_header = Self::diff_header(&filepath, file_change.text);
+        let old_text = file_change.original_text.unwrap_or_default();
+        let new_text = file_change.text.unwrap_or_default();
+        let diff = TextDiff::from_lines(old_text, new_text)
+          .unified_diff()
+          .header(&old_header, &new_header)
+          .to_string();
+
+        FileDiff { filepath, diff }
+      })
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+  };
+
+  use anyhow::Error as AnyhowError;
+  use mkutils::Utils;
+  use serde_json::Value as Json;
+  use ulid::Ulid;
+
+  use super::{LspTextEdit, WorkspaceChange, WorkspaceEditPlan};
+  use crate::{
+    document::Document,
+    types::{Position, Range},
+  };
+
+  fn lsp_text_edit(start_character: usize, end_character: usize, new_text: &str) -> LspTextEdit {
+    let range = Range {
+      start: Position::new(0, start_character),
+      end: Position::new(0, end_character),
+    };
+
+    LspTextEdit {
+      range,
+      new_text: new_text.to_owned(),
+    }
+  }
+
+  fn edited_text(text: &str, edits: Vec<LspTextEdit>) -> String {
+    let edits = WorkspaceEditPlan::sequential_edits(edits).expect("edits should not overlap");
+
+    Document::edit_text(text, &edits).expect("edits should apply")
+  }
+
+  fn temp_dirpath() -> PathBuf {
+    std::env::temp_dir().join(Ulid::new().to_string())
+  }
+
+  fn uri(filepath: &Path) -> String {
+    format!("file://{}", filepath.display())
+  }
+
+  async fn plan(document_changes: &Json) -> Result<WorkspaceEditPlan, AnyhowError> {
+    let workspace_edit = serde_json::json!({ "documentChanges": document_changes });
+
+    WorkspaceEditPlan::new(&workspace_edit, &HashMap::new()).await
+  }
+
+  fn changed_filepaths(workspace_edit_plan: &WorkspaceEditPlan) -> Vec<PathBuf> {
+    workspace_edit_plan
+      .changes()
+      .filter_map(|workspace_change| match workspace_change {
+        WorkspaceChange::File(file_change) => file_change.filepath.to_path_buf().some(),
+        WorkspaceChange::DeletedDirectory(_dirpath) => None,
+      })
+      .collect()
+  }
+
+  #[test]
+  fn edits_are_applied_from_the_end_of_the_text() {
+    let edits = vec![
+      lsp_text_edit(0, 1, "A"),
+      lsp_text_edit(2, 3, "C"),
+      lsp_text_edit(4, 5, "E"),
+    ];
+    let sequential_edits = WorkspaceEditPlan::sequential_edits(edits).expect("edits should not overlap");
+    let start_characters = sequential_edits
+      .iter()
+      .map(|edit| edit.range.start.character)
+      .collect::<Vec<_>>();
+
+    assert_eq!(start_characters, [4, 2, 0]);
+    assert_eq!(
+      edited_text("abcde", vec![lsp_text_edit(4, 5, "E"), lsp_text_edit(0, 1, "A")]),
+      "AbcdE"
+    );
+  }
+
+  #[test]
+  fn insertions_at_the_same_position_keep_their_order() {
+    let edits = vec![
+      lsp_text_edit(1, 1, "x"),
+      lsp_text_edit(1, 1, "y"),
+      lsp_text_edit(1, 2, "B"),
+    ];
+
+    assert_eq!(edited_text("abc", edits), "axyBc");
+  }
+
+  #[test]
+  fn overlapping_edits_are_rejected() {
+    let overlapping_edits = [
+      vec![lsp_text_edit(0, 3, "x"), lsp_text_edit(2, 4, "y")],
+      vec![lsp_text_edit(0, 4, "x"), lsp_text_edit(1, 2, "y")],
+      vec![lsp_text_edit(0, 2, "x"), lsp_text_edit(0, 1, "y")],
+      vec![lsp_text_edit(0, 2, "x"), lsp_text_edit(0, 0, "y")],
+    ];
+
+    for edits in overlapping_edits {
+      assert!(WorkspaceEditPlan::sequential_edits(edits).is_err());
+    }
+
+    assert_eq!(
+      edited_text("abcd", vec![lsp_text_edit(0, 2, "x"), lsp_text_edit(2, 4, "y")]),
+      "xy"
+    );
+  }
+
+  #[tokio::test]
+  async fn create_edit_and_rename_are_planned_in_order() {
+    let dirpath = temp_dirpath();
+    let created_filepath = dirpath.join("Created.lean");
+    let renamed_filepath = dirpath.join("Renamed.lean");
+    let other_filepath = dirpath.join("Other.lean");
+    let document_changes = serde_json::json!([
+      { "kind": "create", "uri": uri(&other_filepath) },
+      { "kind": "create", "uri": uri(&created_filepath) },
+      {
+        "textDocument": { "uri": uri(&created_filepath), "version": null },
+        "edits": [{
+          "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 0, "character": 0 } },
+          "newText": "theorem",
+        }],
+      },
+      { "kind": "rename", "oldUri": uri(&created_filepath), "newUri": uri(&renamed_filepath) },
+    ]);
+    let workspace_edit_plan = plan(&document_changes).await.expect("workspace edit should be planned");
+    let diffs = workspace_edit_plan.diffs(&dirpath);
+
+    assert_eq!(
+      changed_filepaths(&workspace_edit_plan),
+      [other_filepath, renamed_filepath]
+    );
+    assert_eq!(diffs.len(), 2);
+    assert!(diffs[1].diff.contains("+theorem"));
+  }
+
+  #[tokio::test]
+  async fn changes_under_deleted_directories_are_rejected() {
+    let dirpath = temp_dirpath();
+    let filepath = dirpath.join("Main.lean");
+    let delete = serde_json::json!({ "kind": "delete", "uri": uri(&dirpath), "options": { "recursive": true } });
+    let create = serde_json::json!({ "kind": "create", "uri": uri(&filepath) });
+
+    std::fs::create_dir_all(&dirpath).expect("temp dir should be created");
+
+    let create_after_delete = plan(&serde_json::json!([delete, create])).await;
+    let delete_after_create = plan(&serde_json::json!([create, delete])).await;
+    let delete_only = plan(&serde_json::json!([delete])).await;
+
+    std::fs::remove_dir_all(&dirpath).expect("temp dir should be removed");
+
+    assert!(create_after_delete.is_err());
+    assert!(delete_after_create.is_err());
+
+    let workspace_edit_plan = delete_only.expect("deleting the directory alone should be planned");
+    let Some(WorkspaceChange::DeletedDirectory(deleted_dirpath)) = workspace_edit_plan.changes().next() else {
+      panic!("directory should be deleted");
+    };
+
+    assert_eq!(deleted_dirpath, dirpath);
+  }
+}